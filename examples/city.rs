@@ -16,7 +16,7 @@ fn main() {
         let sine = (frame_number as f32 / 150.0).sin().abs();
         let color = (sine * 255f32) as u8;
 
-        window.set_color(100 + color / 3, color, 255 - color, 255);
+        window.set_color((100 + color / 3, color, 255 - color, 255));
         window.draw_image(&mut pic, 0, 0);
 
         frame_number += 1;
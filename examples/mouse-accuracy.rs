@@ -35,7 +35,7 @@ fn main() {
     let program_start_time = SystemTime::now();
 
     while app.next_frame() {
-        app.clear_to_color(130, 130, 130);
+        app.clear_to_color((130, 130, 130));
 
         // move the rect closer to the next_position by 1/5th of the current distance
         rect = Rect::from_center(
@@ -53,7 +53,7 @@ fn main() {
 
         if millis_since_start < COUNTDOWN_TIME_MILLIS {
             // Countdown phase
-            app.set_color(255, 255, 255, 255);
+            app.set_color((255, 255, 255, 255));
             app.print("Get Ready!", WIDTH as i32 / 2 - 50, HEIGHT as i32 / 2 - 30);
             app.print(
                 &format!("{}", 3 - (millis_since_start * 2 / 1000)),
@@ -62,11 +62,11 @@ fn main() {
             );
         } else if millis_since_start < COUNTDOWN_TIME_MILLIS + GAME_TIME_MILLIS {
             // Gameplay phase
-            app.set_color(255, 255, 255, 255);
+            app.set_color((255, 255, 255, 255));
             app.fill_rect(rect);
-            app.set_color(0, 0, 0, 255);
+            app.set_color((0, 0, 0, 255));
             app.draw_rect(rect);
-            app.set_color(255, 255, 255, 255);
+            app.set_color((255, 255, 255, 255));
             app.print(
                 &format!("Successes: {}  Misses: {}", successes, misses),
                 15,
@@ -82,7 +82,7 @@ fn main() {
             );
         } else {
             // Score screen
-            app.set_color(255, 255, 255, 255);
+            app.set_color((255, 255, 255, 255));
             app.print("Time's up!", WIDTH as i32 / 2 - 40, HEIGHT as i32 / 2 - 30);
             app.print(
                 &format!(
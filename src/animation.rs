@@ -0,0 +1,305 @@
+use sprite::SpriteSheet;
+
+/// How long a frame lasts when nothing else says otherwise, in milliseconds.
+const DEFAULT_FRAME_DURATION: u32 = 100;
+
+/// PlayMode decides what an Animation does once it reaches its last frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlayMode {
+    /// Jump back to the first frame and keep playing.
+    Loop,
+
+    /// Play backwards to the first frame, then forwards again, and so on.
+    PingPong,
+
+    /// Stop on the last frame.
+    Once,
+}
+
+/// AnimationEvent is something that happened while an Animation was being updated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AnimationEvent {
+    /// A looping or ping-ponging Animation made it back to its first frame.
+    Looped,
+
+    /// An Animation with `PlayMode::Once` reached the end of its last frame.
+    Finished,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Frame {
+    sprite: usize,
+    duration: u32,
+}
+
+/**
+ * Animation steps through a list of sprites from a SpriteSheet over time.
+ *
+ * An Animation only keeps track of which frame should be showing. Advance it once per frame with
+ * the time that passed, then draw it with `Window::draw_animation`:
+ *
+ * ```rust,no_run
+ * # use simple::*;
+ * # let mut app = Window::new("hello world", 640, 480);
+ * # let image = app.load_image_from_file(std::path::Path::new("walk.png")).unwrap();
+ * let mut sheet = SpriteSheet::from_grid(image, 32, 32);
+ * let mut walk = Animation::new(vec![0, 1, 2, 3], 120, PlayMode::Loop);
+ * while app.next_frame() {
+ *     walk.update(app.frame_time());
+ *     app.draw_animation(&mut sheet, &walk, 100, 100);
+ * }
+ * ```
+ *
+ * Like the Window, an Animation keeps a queue of the events that happened during `update()`. See
+ * `AnimationEvent`.
+ */
+#[derive(Clone, Debug)]
+pub struct Animation {
+    frames: Vec<Frame>,
+    mode: PlayMode,
+
+    // playback state
+    position: usize,
+    elapsed: u32,
+    forwards: bool,
+    finished: bool,
+    paused: bool,
+    event_queue: Vec<AnimationEvent>,
+}
+
+impl Animation {
+    /// Create an Animation that shows each sprite index in `frames` for `frame_duration`
+    /// milliseconds.
+    pub fn new(frames: Vec<usize>, frame_duration: u32, mode: PlayMode) -> Self {
+        Animation::with_durations(
+            frames.into_iter().map(|f| (f, frame_duration)).collect(),
+            mode,
+        )
+    }
+
+    /// Create an Animation from a list of `(sprite index, duration in milliseconds)` pairs.
+    pub fn with_durations(frames: Vec<(usize, u32)>, mode: PlayMode) -> Self {
+        Animation {
+            frames: frames
+                .into_iter()
+                .map(|(sprite, duration)| Frame { sprite, duration })
+                .collect(),
+            mode,
+            position: 0,
+            elapsed: 0,
+            forwards: true,
+            finished: false,
+            paused: false,
+            event_queue: vec![],
+        }
+    }
+
+    /// Create an Animation over the given sprites of the sheet, using the durations stored in the
+    /// sheet. Sprites without a duration are shown for 100 milliseconds.
+    ///
+    /// NOTE: This function panics if any index is out of range for the sheet.
+    pub fn from_sheet(sheet: &SpriteSheet, frames: Vec<usize>, mode: PlayMode) -> Self {
+        let frames = frames
+            .into_iter()
+            .map(|i| {
+                let sprite = sheet.get(i).expect("animation frame out of range");
+                (i, sprite.duration.unwrap_or(DEFAULT_FRAME_DURATION))
+            })
+            .collect();
+        Animation::with_durations(frames, mode)
+    }
+
+    /// Advance the Animation by `elapsed` milliseconds. Usually you will pass
+    /// `Window::frame_time()` here once per frame.
+    pub fn update(&mut self, elapsed: u32) {
+        if self.paused || self.finished || self.frames.is_empty() {
+            return;
+        }
+
+        self.elapsed += elapsed;
+        loop {
+            // A frame must last at least a millisecond, otherwise this would never finish.
+            let duration = self.frames[self.position].duration.max(1);
+            if self.elapsed < duration {
+                break;
+            }
+            self.elapsed -= duration;
+            self.advance();
+            if self.finished {
+                self.elapsed = 0;
+                break;
+            }
+        }
+    }
+
+    /// Move to the next frame according to the PlayMode.
+    fn advance(&mut self) {
+        let last = self.frames.len() - 1;
+        match self.mode {
+            PlayMode::Loop => {
+                if self.position == last {
+                    self.position = 0;
+                    self.event_queue.push(AnimationEvent::Looped);
+                } else {
+                    self.position += 1;
+                }
+            }
+            PlayMode::Once => {
+                if self.position == last {
+                    self.finished = true;
+                    self.event_queue.push(AnimationEvent::Finished);
+                } else {
+                    self.position += 1;
+                }
+            }
+            PlayMode::PingPong => {
+                if last == 0 {
+                    self.event_queue.push(AnimationEvent::Looped);
+                } else if self.forwards {
+                    if self.position == last {
+                        self.forwards = false;
+                        self.position -= 1;
+                    } else {
+                        self.position += 1;
+                    }
+                } else {
+                    self.position -= 1;
+                    if self.position == 0 {
+                        self.forwards = true;
+                        self.event_queue.push(AnimationEvent::Looped);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Return the index in the SpriteSheet of the frame that should be showing right now.
+    ///
+    /// NOTE: This function panics if the Animation has no frames.
+    pub fn current_sprite(&self) -> usize {
+        self.frames[self.position].sprite
+    }
+
+    /// Return the position of the current frame within the Animation, starting at 0.
+    pub fn current_frame(&self) -> usize {
+        self.position
+    }
+
+    /// Return the number of frames in the Animation.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Return true if the Animation has no frames.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Return the PlayMode of the Animation.
+    pub fn mode(&self) -> PlayMode {
+        self.mode
+    }
+
+    /// Change the PlayMode. Playback continues from the current frame.
+    pub fn set_mode(&mut self, mode: PlayMode) {
+        self.mode = mode;
+        self.forwards = true;
+        self.finished = false;
+    }
+
+    /// Return true once an Animation with `PlayMode::Once` has played all of its frames.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Stop advancing the Animation in `update()` until `resume()` is called.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Undo `pause()`.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Return true if the Animation is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Go back to the first frame and play from the start. Pending events are discarded.
+    pub fn restart(&mut self) {
+        self.position = 0;
+        self.elapsed = 0;
+        self.forwards = true;
+        self.finished = false;
+        self.event_queue.clear();
+    }
+
+    /// Return true when there is an event waiting in the queue for processing.
+    pub fn has_event(&self) -> bool {
+        !self.event_queue.is_empty()
+    }
+
+    /// Get the next event from the queue. NOTE: If the event queue is empty, this function will
+    /// panic. Call `has_event()` to find out if there is an event ready for processing.
+    pub fn next_event(&mut self) -> AnimationEvent {
+        self.event_queue.remove(0)
+    }
+}
+
+#[cfg(test)]
+fn sprites_shown(animation: &mut Animation, steps: usize, step: u32) -> Vec<usize> {
+    let mut shown = vec![animation.current_sprite()];
+    for _ in 0..steps {
+        animation.update(step);
+        shown.push(animation.current_sprite());
+    }
+    shown
+}
+
+#[test]
+fn animation_loop() {
+    let mut a = Animation::new(vec![4, 5, 6], 100, PlayMode::Loop);
+    assert_eq!(sprites_shown(&mut a, 4, 100), vec![4, 5, 6, 4, 5]);
+    assert!(a.has_event());
+    assert_eq!(a.next_event(), AnimationEvent::Looped);
+    assert!(!a.has_event());
+
+    // time that doesn't add up to a whole frame carries over
+    let mut a = Animation::new(vec![0, 1], 100, PlayMode::Loop);
+    assert_eq!(sprites_shown(&mut a, 4, 60), vec![0, 0, 1, 1, 0]);
+}
+
+#[test]
+fn animation_once() {
+    let mut a = Animation::with_durations(vec![(0, 50), (1, 200)], PlayMode::Once);
+    a.update(1_000);
+    assert_eq!(a.current_sprite(), 1);
+    assert!(a.is_finished());
+    assert_eq!(a.next_event(), AnimationEvent::Finished);
+    assert!(!a.has_event());
+
+    a.restart();
+    assert_eq!(a.current_sprite(), 0);
+    assert!(!a.is_finished());
+}
+
+#[test]
+fn animation_ping_pong() {
+    let mut a = Animation::new(vec![0, 1, 2], 10, PlayMode::PingPong);
+    assert_eq!(sprites_shown(&mut a, 6, 10), vec![0, 1, 2, 1, 0, 1, 2]);
+    assert_eq!(a.next_event(), AnimationEvent::Looped);
+    assert!(!a.has_event());
+}
+
+#[test]
+fn animation_pause() {
+    let mut a = Animation::new(vec![0, 1], 10, PlayMode::Loop);
+    a.pause();
+    a.update(100);
+    assert_eq!(a.current_sprite(), 0);
+    a.resume();
+    a.update(10);
+    assert_eq!(a.current_sprite(), 1);
+}
@@ -0,0 +1,702 @@
+/*!
+ * Reading Aseprite's native `.aseprite`/`.ase` files.
+ *
+ * The file format is documented at
+ * https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md
+ *
+ * Only what is needed to display the sprite is read: every visible image layer is flattened into
+ * one RGBA image per frame, using normal blending and the layer and cel opacity. Other blend
+ * modes are treated as normal. Tilemap layers, slices and user data are ignored.
+ */
+
+use std::collections::HashMap;
+
+use miniz_oxide::inflate;
+
+use animation::{Animation, PlayMode};
+use sprite::SpriteSheet;
+use util;
+
+const HEADER_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+
+const CHUNK_OLD_PALETTE: u16 = 0x0004;
+const CHUNK_LAYER: u16 = 0x2004;
+const CHUNK_CEL: u16 = 0x2005;
+const CHUNK_TAGS: u16 = 0x2018;
+const CHUNK_PALETTE: u16 = 0x2019;
+
+const LAYER_VISIBLE: u16 = 1;
+const LAYER_BACKGROUND: u16 = 2;
+const LAYER_REFERENCE: u16 = 64;
+
+/**
+ * AsepriteFile is everything `Window::load_aseprite` reads out of an Aseprite file.
+ *
+ * Every frame of the file becomes one sprite of `sheet`, in order, with the frame duration set in
+ * Aseprite. Every tag becomes an entry in `animations`.
+ */
+pub struct AsepriteFile {
+    /// One sprite per frame of the file.
+    pub sheet: SpriteSheet,
+
+    /// One Animation per tag, keyed by the name of the tag. Tags set to play in reverse are
+    /// turned around, and ping-pong tags use `PlayMode::PingPong`.
+    pub animations: HashMap<String, Animation>,
+}
+
+impl AsepriteFile {
+    /// Return a fresh copy of the Animation for the tag called `name`.
+    pub fn animation(&self, name: &str) -> Option<Animation> {
+        self.animations.get(name).cloned()
+    }
+
+    /// Return an Animation that loops over every frame of the file.
+    pub fn all_frames(&self) -> Animation {
+        Animation::from_sheet(&self.sheet, (0..self.sheet.len()).collect(), PlayMode::Loop)
+    }
+}
+
+/// The decoded contents of an Aseprite file, before any textures are created.
+pub struct Document {
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<Frame>,
+    pub tags: Vec<Tag>,
+}
+
+/// One flattened frame: `width * height` RGBA pixels.
+pub struct Frame {
+    pub pixels: Vec<u8>,
+    pub duration: u32,
+}
+
+/// A named range of frames, inclusive on both ends.
+pub struct Tag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    pub mode: PlayMode,
+    pub reverse: bool,
+}
+
+impl Tag {
+    /// Build the Animation this tag describes, over the sprites of `sheet`.
+    pub fn to_animation(&self, sheet: &SpriteSheet) -> Animation {
+        let mut frames: Vec<usize> = (self.from..=self.to).collect();
+        if self.reverse {
+            frames.reverse();
+        }
+        Animation::from_sheet(sheet, frames, self.mode)
+    }
+}
+
+struct Layer {
+    flags: u16,
+    layer_type: u16,
+    opacity: u8,
+    visible: bool,
+}
+
+/// The pixels of one cel, already converted to RGBA.
+#[derive(Clone)]
+struct Cel {
+    layer: usize,
+    x: i32,
+    y: i32,
+    z_index: i32,
+    opacity: u8,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+/// Reads little-endian values out of a byte slice.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, position: 0 }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.position + count > self.data.len() {
+            return Err("aseprite file ends unexpectedly".to_string());
+        }
+        let bytes = &self.data[self.position..self.position + count];
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, count: usize) -> Result<(), String> {
+        self.bytes(count).map(|_| ())
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn word(&mut self) -> Result<u16, String> {
+        let b = self.bytes(2)?;
+        Ok(b[0] as u16 | (b[1] as u16) << 8)
+    }
+
+    fn short(&mut self) -> Result<i16, String> {
+        Ok(self.word()? as i16)
+    }
+
+    fn dword(&mut self) -> Result<u32, String> {
+        let b = self.bytes(4)?;
+        Ok(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.word()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+    }
+}
+
+/// Decode an Aseprite file and flatten its visible layers.
+pub fn parse(data: &[u8]) -> Result<Document, String> {
+    let mut r = Reader::new(data);
+    r.skip(4)?; // file size
+    if r.word()? != HEADER_MAGIC {
+        return Err("not an aseprite file".to_string());
+    }
+    let num_frames = r.word()? as usize;
+    let width = r.word()? as u32;
+    let height = r.word()? as u32;
+    let depth = r.word()?;
+    let flags = r.dword()?;
+    r.skip(2 + 4 + 4)?; // speed, reserved
+    let transparent_index = r.byte()?;
+    r.skip(3)?;
+    r.skip(128 - r.position)?;
+
+    if depth != 32 && depth != 16 && depth != 8 {
+        return Err(format!("unsupported aseprite color depth {}", depth));
+    }
+    let frame_len =
+        util::rgba_len(width, height).ok_or_else(|| "aseprite file is too big".to_string())?;
+    let layer_opacity_valid = flags & 1 != 0;
+
+    let mut layers: Vec<Layer> = vec![];
+    // visibility of the group at each child level, for hiding the children of hidden groups
+    let mut group_visible: Vec<bool> = vec![];
+    let mut palette: Vec<[u8; 4]> = vec![[0, 0, 0, 255]; 256];
+    // The old palette chunk is only used when the file has no new-style palette chunk.
+    let mut old_palette = palette.clone();
+    let mut has_new_palette = false;
+    let mut tags = vec![];
+    let mut frames = vec![];
+    // every cel seen so far, by (frame, layer), so linked cels can be resolved
+    let mut cels: HashMap<(usize, usize), Cel> = HashMap::new();
+
+    for frame_index in 0..num_frames {
+        let frame_start = r.position;
+        let frame_size = r.dword()? as usize;
+        if r.word()? != FRAME_MAGIC {
+            return Err(format!("aseprite frame {} is corrupt", frame_index));
+        }
+        let old_chunk_count = r.word()? as usize;
+        let duration = r.word()? as u32;
+        r.skip(2)?;
+        let chunk_count = match r.dword()? as usize {
+            0 => old_chunk_count,
+            n => n,
+        };
+
+        let mut frame_cels = vec![];
+        for _ in 0..chunk_count {
+            let chunk_start = r.position;
+            let chunk_size = r.dword()? as usize;
+            let chunk_type = r.word()?;
+            if chunk_size < 6 {
+                return Err("aseprite chunk is corrupt".to_string());
+            }
+            let mut c = Reader::new(r.bytes(chunk_size - 6)?);
+
+            match chunk_type {
+                CHUNK_LAYER => {
+                    let flags = c.word()?;
+                    let layer_type = c.word()?;
+                    let level = c.word()? as usize;
+                    c.skip(2 + 2 + 2)?; // default size, blend mode
+                    let opacity = c.byte()?;
+
+                    group_visible.truncate(level);
+                    let parent_visible = group_visible.iter().all(|&v| v);
+                    let visible = parent_visible
+                        && flags & LAYER_VISIBLE != 0
+                        && flags & LAYER_REFERENCE == 0;
+                    group_visible.push(visible);
+
+                    layers.push(Layer {
+                        flags,
+                        layer_type,
+                        opacity: if layer_opacity_valid { opacity } else { 255 },
+                        visible,
+                    });
+                }
+
+                CHUNK_CEL => {
+                    let layer = c.word()? as usize;
+                    let x = c.short()? as i32;
+                    let y = c.short()? as i32;
+                    let opacity = c.byte()?;
+                    let cel_type = c.word()?;
+                    let z_index = c.short()? as i32;
+                    c.skip(5)?;
+
+                    let cel = match cel_type {
+                        // raw or compressed image
+                        0 | 2 => {
+                            let w = c.word()? as u32;
+                            let h = c.word()? as u32;
+                            // Every size below is at most this big.
+                            util::rgba_len(w, h)
+                                .ok_or_else(|| "aseprite cel is too big".to_string())?;
+                            let count = w as usize * h as usize;
+                            let rest = &c.data[c.position..];
+                            let raw = if cel_type == 0 {
+                                rest.to_vec()
+                            } else {
+                                // Never inflate more than the cel can use.
+                                let size = count * (depth / 8) as usize;
+                                inflate::decompress_to_vec_zlib_with_limit(rest, size)
+                                    .map_err(|e| format!("aseprite cel is corrupt: {}", e))?
+                            };
+                            let background = layers
+                                .get(layer)
+                                .is_some_and(|l| l.flags & LAYER_BACKGROUND != 0);
+                            let pixels = to_rgba(
+                                &raw,
+                                count,
+                                depth,
+                                if has_new_palette {
+                                    &palette
+                                } else {
+                                    &old_palette
+                                },
+                                transparent_index,
+                                background,
+                            )?;
+                            Cel {
+                                layer,
+                                x,
+                                y,
+                                z_index,
+                                opacity,
+                                width: w,
+                                height: h,
+                                pixels,
+                            }
+                        }
+
+                        // linked to the cel on the same layer in another frame
+                        1 => {
+                            let linked_frame = c.word()? as usize;
+                            match cels.get(&(linked_frame, layer)) {
+                                Some(linked) => Cel {
+                                    x,
+                                    y,
+                                    z_index,
+                                    opacity,
+                                    ..linked.clone()
+                                },
+                                None => continue,
+                            }
+                        }
+
+                        // compressed tilemap
+                        _ => continue,
+                    };
+                    cels.insert((frame_index, layer), cel.clone());
+                    frame_cels.push(cel);
+                }
+
+                CHUNK_PALETTE => {
+                    // Indexed pixels are one byte, so no more than 256 colors can be used.
+                    if c.dword()? > 256 {
+                        return Err("aseprite palette has more than 256 colors".to_string());
+                    }
+                    let first = c.dword()? as usize;
+                    let last = c.dword()? as usize;
+                    c.skip(8)?;
+                    has_new_palette = true;
+                    for i in first..=last {
+                        let flags = c.word()?;
+                        let rgba = c.bytes(4)?;
+                        if i < palette.len() {
+                            palette[i] = [rgba[0], rgba[1], rgba[2], rgba[3]];
+                        }
+                        if flags & 1 != 0 {
+                            c.string()?;
+                        }
+                    }
+                }
+
+                CHUNK_OLD_PALETTE => {
+                    let packets = c.word()?;
+                    let mut index = 0;
+                    for _ in 0..packets {
+                        index += c.byte()? as usize;
+                        let count = match c.byte()? {
+                            0 => 256,
+                            n => n as usize,
+                        };
+                        for _ in 0..count {
+                            let rgb = c.bytes(3)?;
+                            if index < old_palette.len() {
+                                old_palette[index] = [rgb[0], rgb[1], rgb[2], 255];
+                            }
+                            index += 1;
+                        }
+                    }
+                }
+
+                CHUNK_TAGS => {
+                    let count = c.word()?;
+                    c.skip(8)?;
+                    for _ in 0..count {
+                        let from = c.word()? as usize;
+                        let to = c.word()? as usize;
+                        let direction = c.byte()?;
+                        c.skip(2 + 6 + 3 + 1)?; // repeat, reserved, color
+                        let name = c.string()?;
+                        tags.push(Tag {
+                            name,
+                            from: from.min(to),
+                            to: to.max(from),
+                            mode: if direction >= 2 {
+                                PlayMode::PingPong
+                            } else {
+                                PlayMode::Loop
+                            },
+                            // 1 is reverse, 3 is ping-pong starting backwards
+                            reverse: direction == 1 || direction == 3,
+                        });
+                    }
+                }
+
+                _ => (),
+            }
+
+            r.position = chunk_start + chunk_size;
+        }
+        r.position = frame_start + frame_size;
+
+        // Flatten: lower layers first, with each cel's z-index moving it up or down the stack.
+        frame_cels.sort_by_key(|cel| (cel.layer as i32 + cel.z_index, cel.z_index));
+        let mut pixels = vec![0; frame_len];
+        for cel in &frame_cels {
+            let layer = match layers.get(cel.layer) {
+                Some(l) => l,
+                None => continue,
+            };
+            if !layer.visible || layer.layer_type != 0 {
+                continue;
+            }
+            let opacity = (cel.opacity as u32 * layer.opacity as u32 + 127) / 255;
+            blend_cel(&mut pixels, width, height, cel, opacity as u8);
+        }
+        frames.push(Frame { pixels, duration });
+    }
+
+    if let Some(tag) = tags.iter().find(|tag| tag.to >= frames.len()) {
+        return Err(format!(
+            "aseprite tag {:?} ends after the last frame",
+            tag.name
+        ));
+    }
+
+    Ok(Document {
+        width,
+        height,
+        frames,
+        tags,
+    })
+}
+
+/// Convert `count` pixels from the file's color depth to RGBA.
+fn to_rgba(
+    raw: &[u8],
+    count: usize,
+    depth: u16,
+    palette: &[[u8; 4]],
+    transparent_index: u8,
+    background: bool,
+) -> Result<Vec<u8>, String> {
+    let bytes_per_pixel = (depth / 8) as usize;
+    if raw.len() < count * bytes_per_pixel {
+        return Err("aseprite cel has too few pixels".to_string());
+    }
+    let mut pixels = Vec::with_capacity(count * 4);
+    for p in raw.chunks(bytes_per_pixel).take(count) {
+        match depth {
+            32 => pixels.extend_from_slice(p),
+            16 => pixels.extend_from_slice(&[p[0], p[0], p[0], p[1]]),
+            _ => {
+                if p[0] == transparent_index && !background {
+                    pixels.extend_from_slice(&[0, 0, 0, 0]);
+                } else {
+                    let color = palette.get(p[0] as usize).cloned().unwrap_or([0, 0, 0, 0]);
+                    pixels.extend_from_slice(&color);
+                }
+            }
+        }
+    }
+    Ok(pixels)
+}
+
+/// Draw the cel over `pixels` with normal alpha blending.
+fn blend_cel(pixels: &mut [u8], width: u32, height: u32, cel: &Cel, opacity: u8) {
+    for cy in 0..cel.height as i32 {
+        let y = cel.y + cy;
+        if y < 0 || y >= height as i32 {
+            continue;
+        }
+        for cx in 0..cel.width as i32 {
+            let x = cel.x + cx;
+            if x < 0 || x >= width as i32 {
+                continue;
+            }
+            let src = (cy as usize * cel.width as usize + cx as usize) * 4;
+            let dst = (y as usize * width as usize + x as usize) * 4;
+            let src = &cel.pixels[src..src + 4];
+
+            let src_a = src[3] as f32 / 255.0 * opacity as f32 / 255.0;
+            let dst_a = pixels[dst + 3] as f32 / 255.0;
+            let out_a = src_a + dst_a * (1.0 - src_a);
+            if out_a <= 0.0 {
+                continue;
+            }
+            for i in 0..3 {
+                let c = (src[i] as f32 * src_a + pixels[dst + i] as f32 * dst_a * (1.0 - src_a))
+                    / out_a;
+                pixels[dst + i] = c.round() as u8;
+            }
+            pixels[dst + 3] = (out_a * 255.0).round() as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_file(depth: u16, chunks: Vec<(u16, Vec<u8>)>, frames: usize) -> Vec<u8> {
+    let mut frame = vec![];
+    for (kind, data) in &chunks {
+        frame.extend_from_slice(&(data.len() as u32 + 6).to_le_bytes());
+        frame.extend_from_slice(&kind.to_le_bytes());
+        frame.extend_from_slice(data);
+    }
+
+    let mut file = vec![0; 128];
+    file[4..6].copy_from_slice(&HEADER_MAGIC.to_le_bytes());
+    file[6..8].copy_from_slice(&(frames as u16).to_le_bytes());
+    file[8..10].copy_from_slice(&2u16.to_le_bytes()); // width
+    file[10..12].copy_from_slice(&2u16.to_le_bytes()); // height
+    file[12..14].copy_from_slice(&depth.to_le_bytes());
+    file[14..18].copy_from_slice(&1u32.to_le_bytes()); // layer opacity is valid
+    for i in 0..frames {
+        // only the first frame has chunks
+        let (data, count): (&[u8], usize) = if i == 0 {
+            (&frame, chunks.len())
+        } else {
+            (&[], 0)
+        };
+        file.extend_from_slice(&(data.len() as u32 + 16).to_le_bytes());
+        file.extend_from_slice(&FRAME_MAGIC.to_le_bytes());
+        file.extend_from_slice(&0u16.to_le_bytes());
+        file.extend_from_slice(&(100 * (i as u16 + 1)).to_le_bytes()); // duration
+        file.extend_from_slice(&[0, 0]);
+        file.extend_from_slice(&(count as u32).to_le_bytes());
+        file.extend_from_slice(data);
+    }
+    file
+}
+
+#[cfg(test)]
+fn layer_chunk(flags: u16, opacity: u8, name: &str) -> (u16, Vec<u8>) {
+    let mut data = vec![];
+    data.extend_from_slice(&flags.to_le_bytes());
+    data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0]); // type, level, size, blend mode
+    data.push(opacity);
+    data.extend_from_slice(&[0, 0, 0]);
+    data.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    data.extend_from_slice(name.as_bytes());
+    (CHUNK_LAYER, data)
+}
+
+#[cfg(test)]
+fn raw_cel_chunk(layer: u16, x: i16, y: i16, w: u16, h: u16, pixels: &[u8]) -> (u16, Vec<u8>) {
+    let mut data = vec![];
+    data.extend_from_slice(&layer.to_le_bytes());
+    data.extend_from_slice(&x.to_le_bytes());
+    data.extend_from_slice(&y.to_le_bytes());
+    data.push(255); // opacity
+    data.extend_from_slice(&0u16.to_le_bytes()); // raw
+    data.extend_from_slice(&[0; 7]); // z-index, reserved
+    data.extend_from_slice(&w.to_le_bytes());
+    data.extend_from_slice(&h.to_le_bytes());
+    data.extend_from_slice(pixels);
+    (CHUNK_CEL, data)
+}
+
+#[test]
+fn parse_flattens_visible_layers() {
+    let red = [255, 0, 0, 255];
+    let blue = [0, 0, 255, 255];
+    let file = test_file(
+        32,
+        vec![
+            layer_chunk(LAYER_VISIBLE, 255, "bottom"),
+            layer_chunk(LAYER_VISIBLE, 255, "top"),
+            layer_chunk(0, 255, "hidden"),
+            raw_cel_chunk(0, 0, 0, 2, 1, &[red, red].concat()),
+            raw_cel_chunk(1, 1, 0, 1, 2, &[blue, blue].concat()),
+            raw_cel_chunk(2, 0, 0, 2, 2, &[red, red, red, red].concat()),
+        ],
+        2,
+    );
+
+    let doc = parse(&file).unwrap();
+    assert_eq!((doc.width, doc.height), (2, 2));
+    assert_eq!(doc.frames.len(), 2);
+    assert_eq!(doc.frames[0].duration, 100);
+    assert_eq!(doc.frames[1].duration, 200);
+    assert_eq!(
+        doc.frames[0].pixels,
+        [red, blue, [0, 0, 0, 0], blue].concat()
+    );
+    assert_eq!(doc.frames[1].pixels, vec![0; 16]);
+}
+
+#[test]
+fn parse_indexed_and_tags() {
+    let mut palette = vec![];
+    palette.extend_from_slice(&2u32.to_le_bytes()); // size
+    palette.extend_from_slice(&0u32.to_le_bytes()); // first
+    palette.extend_from_slice(&1u32.to_le_bytes()); // last
+    palette.extend_from_slice(&[0; 8]);
+    palette.extend_from_slice(&[0, 0, 1, 2, 3, 255]);
+    palette.extend_from_slice(&[0, 0, 10, 20, 30, 128]);
+
+    let mut tags = vec![];
+    tags.extend_from_slice(&1u16.to_le_bytes());
+    tags.extend_from_slice(&[0; 8]);
+    tags.extend_from_slice(&0u16.to_le_bytes());
+    tags.extend_from_slice(&1u16.to_le_bytes());
+    tags.push(2); // ping-pong
+    tags.extend_from_slice(&[0; 12]);
+    tags.extend_from_slice(&4u16.to_le_bytes());
+    tags.extend_from_slice(b"walk");
+
+    // the old palette chunk is ignored when there is a new one
+    let mut old_palette = vec![];
+    old_palette.extend_from_slice(&1u16.to_le_bytes()); // packets
+    old_palette.extend_from_slice(&[1, 1, 99, 99, 99]); // skip 1, then 1 color
+
+    let file = test_file(
+        8,
+        vec![
+            (CHUNK_OLD_PALETTE, old_palette.clone()),
+            (CHUNK_PALETTE, palette.clone()),
+            layer_chunk(LAYER_VISIBLE, 255, "layer"),
+            raw_cel_chunk(0, 0, 0, 2, 2, &[0, 1, 1, 0]),
+            (CHUNK_TAGS, tags),
+        ],
+        2,
+    );
+
+    let doc = parse(&file).unwrap();
+    // index 0 is the transparent color
+    assert_eq!(
+        doc.frames[0].pixels,
+        [
+            [0, 0, 0, 0],
+            [10, 20, 30, 128],
+            [10, 20, 30, 128],
+            [0, 0, 0, 0]
+        ]
+        .concat()
+    );
+    assert_eq!(doc.tags.len(), 1);
+    assert_eq!(doc.tags[0].name, "walk");
+    assert_eq!((doc.tags[0].from, doc.tags[0].to), (0, 1));
+    assert_eq!(doc.tags[0].mode, PlayMode::PingPong);
+
+    // but used when there isn't
+    let file = test_file(
+        8,
+        vec![
+            (CHUNK_OLD_PALETTE, old_palette),
+            layer_chunk(LAYER_VISIBLE, 255, "layer"),
+            raw_cel_chunk(0, 0, 0, 2, 2, &[0, 1, 1, 0]),
+        ],
+        1,
+    );
+    let doc = parse(&file).unwrap();
+    assert_eq!(doc.frames[0].pixels[4..8], [99, 99, 99, 255]);
+
+    // a palette bigger than an index can reach is corrupt
+    palette[..4].copy_from_slice(&0x4000_0000u32.to_le_bytes());
+    let file = test_file(8, vec![(CHUNK_PALETTE, palette)], 1);
+    assert!(parse(&file).is_err());
+}
+
+#[test]
+fn parse_compressed_cel() {
+    let red = [255, 0, 0, 255];
+    let raw = [red, red, red, red].concat();
+    let mut data = vec![];
+    data.extend_from_slice(&0u16.to_le_bytes()); // layer
+    data.extend_from_slice(&[0; 4]); // x, y
+    data.push(255); // opacity
+    data.extend_from_slice(&2u16.to_le_bytes()); // compressed
+    data.extend_from_slice(&[0; 7]); // z-index, reserved
+    data.extend_from_slice(&2u16.to_le_bytes());
+    data.extend_from_slice(&2u16.to_le_bytes());
+    data.extend_from_slice(&miniz_oxide::deflate::compress_to_vec_zlib(&raw, 6));
+
+    let file = test_file(
+        32,
+        vec![layer_chunk(LAYER_VISIBLE, 255, "layer"), (CHUNK_CEL, data)],
+        1,
+    );
+    assert_eq!(parse(&file).unwrap().frames[0].pixels, raw);
+
+    // corrupt data is an error, not a panic
+    let mut file = file;
+    let length = file.len();
+    file[length - 6] ^= 0xff;
+    assert!(parse(&file).is_err());
+}
+
+#[test]
+fn parse_rejects_tags_past_the_last_frame() {
+    let mut tags = vec![];
+    tags.extend_from_slice(&1u16.to_le_bytes());
+    tags.extend_from_slice(&[0; 8]);
+    tags.extend_from_slice(&0u16.to_le_bytes());
+    tags.extend_from_slice(&5u16.to_le_bytes()); // only 2 frames
+    tags.push(0);
+    tags.extend_from_slice(&[0; 12]);
+    tags.extend_from_slice(&4u16.to_le_bytes());
+    tags.extend_from_slice(b"walk");
+
+    let file = test_file(
+        32,
+        vec![layer_chunk(LAYER_VISIBLE, 255, "layer"), (CHUNK_TAGS, tags)],
+        2,
+    );
+    assert!(parse(&file).is_err());
+}
+
+#[test]
+fn parse_rejects_garbage() {
+    assert!(parse(&[]).is_err());
+    assert!(parse(&[0; 128]).is_err());
+}
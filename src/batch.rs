@@ -0,0 +1,332 @@
+/*!
+ * This batch module holds `BatchMode`. Everything else in it is for internal library use only. See
+ * `Window::begin_batch`.
+ *
+ * A Batch collects draw calls instead of drawing them straight away. When it is flushed, the draw
+ * calls are sorted by layer. Within a layer, a batch either keeps the order they were made in, or
+ * moves triangles that share a texture, blend mode and clip rectangle next to each other. Runs of
+ * triangles that share all of those are drawn with one call to SDL each.
+ *
+ * Textures are remembered by their raw pointers. That is safe because textures are never freed
+ * while the Window is alive, except by `Window::upload_image`, which flushes the batch first.
+ */
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::ptr;
+
+extern crate sdl2;
+use sdl2::pixels;
+use sdl2::render;
+use sdl2::sys;
+
+use blend::{self, BlendMode};
+use geometry::{self, RawVertex};
+use shape;
+
+/// The indices of the two triangles that make up a quad whose corners are listed in order.
+pub const QUAD_INDICES: [i32; 6] = [0, 1, 2, 0, 2, 3];
+
+/**
+ * BatchMode decides whether `Window::begin_batch` may change the order of draw calls within a
+ * layer. Layers are always drawn from lowest to highest, whichever mode is used.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BatchMode {
+    /// Draw calls on the same layer are drawn in the order they were made in. Use this to let
+    /// independent parts of a game, like the background, the entities and the UI, draw in any
+    /// order, as long as each uses its own layer.
+    KeepOrder,
+
+    /// Draw calls on the same layer that use the same image, BlendMode and clip rectangle are
+    /// moved next to each other, so that they can be drawn with as few calls to SDL as possible.
+    /// This makes a big difference when drawing thousands of sprites, but images that overlap on
+    /// the same layer may come out in a different order than they were drawn. Put them on
+    /// different layers if that matters.
+    GroupByImage,
+}
+
+/// The Window state that a draw call is made with.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct State {
+    pub layer: i32,
+    pub blend_mode: BlendMode,
+    pub clip: Option<shape::Rect>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum Kind {
+    Triangles,
+    Lines,
+    Points,
+}
+
+/// One draw call that was added to the batch.
+struct Item {
+    state: State,
+    kind: Kind,
+    texture: *mut sys::SDL_Texture,
+    vertices: Range<usize>,
+    indices: Range<usize>,
+}
+
+impl Item {
+    /// Return true if the two items can be drawn with one call to SDL.
+    fn can_merge(&self, other: &Item) -> bool {
+        self.kind == Kind::Triangles
+            && other.kind == Kind::Triangles
+            && self.state == other.state
+            && self.texture == other.texture
+    }
+}
+
+pub struct Batch {
+    mode: BatchMode,
+    vertices: Vec<RawVertex>,
+    indices: Vec<i32>,
+    items: Vec<Item>,
+}
+
+impl Batch {
+    /// Create an empty Batch that orders draw calls according to `mode`.
+    pub fn new(mode: BatchMode) -> Batch {
+        Batch {
+            mode,
+            vertices: vec![],
+            indices: vec![],
+            items: vec![],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Throw away everything in the batch without drawing it.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.items.clear();
+    }
+
+    /// Add triangles to the batch. `indices` refer to `vertices`, the same way as for
+    /// `geometry::render_geometry`.
+    pub fn push(
+        &mut self,
+        state: State,
+        texture: Option<&render::Texture>,
+        vertices: &[RawVertex],
+        indices: &[i32],
+    ) {
+        let first_index = self.indices.len();
+        if indices.is_empty() {
+            self.indices.extend(0..vertices.len() as i32);
+        } else {
+            self.indices.extend_from_slice(indices);
+        }
+        let texture = texture.map_or(ptr::null_mut(), |t| t.raw());
+        self.push_item(state, Kind::Triangles, texture, vertices, first_index);
+    }
+
+    /// Add a line strip through `points` to the batch.
+    pub fn push_lines(&mut self, state: State, points: &[shape::Point], color: pixels::Color) {
+        let vertices = point_vertices(points, color);
+        let first_index = self.indices.len();
+        self.push_item(state, Kind::Lines, ptr::null_mut(), &vertices, first_index);
+    }
+
+    /// Add single pixels at `points` to the batch.
+    pub fn push_points(&mut self, state: State, points: &[shape::Point], color: pixels::Color) {
+        let vertices = point_vertices(points, color);
+        let first_index = self.indices.len();
+        self.push_item(state, Kind::Points, ptr::null_mut(), &vertices, first_index);
+    }
+
+    fn push_item(
+        &mut self,
+        state: State,
+        kind: Kind,
+        texture: *mut sys::SDL_Texture,
+        vertices: &[RawVertex],
+        first_index: usize,
+    ) {
+        if vertices.is_empty() {
+            self.indices.truncate(first_index);
+            return;
+        }
+        let first_vertex = self.vertices.len();
+        self.vertices.extend_from_slice(vertices);
+        self.items.push(Item {
+            state,
+            kind,
+            texture,
+            vertices: first_vertex..self.vertices.len(),
+            indices: first_index..self.indices.len(),
+        });
+    }
+
+    /// Return the order to draw the items in. Items are sorted by layer. Unless the order is kept,
+    /// triangles within a layer that could be drawn together are moved next to each other, at
+    /// the position of the first of them.
+    fn order(&self) -> Vec<usize> {
+        let mut first_seen = HashMap::new();
+        let keys: Vec<(i32, usize)> = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                if self.mode == BatchMode::KeepOrder || item.kind != Kind::Triangles {
+                    return (item.state.layer, i);
+                }
+                let group = (item.state, item.texture);
+                (item.state.layer, *first_seen.entry(group).or_insert(i))
+            })
+            .collect();
+
+        let mut order: Vec<usize> = (0..self.items.len()).collect();
+        order.sort_by_key(|&i| keys[i]);
+        order
+    }
+
+    /// Draw everything in the batch onto the canvas and empty it. This leaves textures with a
+    /// white color mod, and the canvas with an unknown draw color, draw blend mode and clip
+    /// rectangle.
+    pub fn flush(
+        &mut self,
+        canvas: &mut render::Canvas<sdl2::video::Window>,
+    ) -> Result<(), String> {
+        let order = self.order();
+        let mut vertices = vec![];
+        let mut indices = vec![];
+        let mut clip = None;
+        canvas.set_clip_rect(None);
+
+        let mut run_start = 0;
+        while run_start < order.len() {
+            let first = &self.items[order[run_start]];
+            let mut run_end = run_start;
+            vertices.clear();
+            indices.clear();
+            while run_end < order.len() {
+                let item = &self.items[order[run_end]];
+                if run_end > run_start && !first.can_merge(item) {
+                    break;
+                }
+                // each item's indices count from its own first vertex
+                let base = vertices.len() as i32;
+                vertices.extend_from_slice(&self.vertices[item.vertices.clone()]);
+                indices.extend(
+                    self.indices[item.indices.clone()]
+                        .iter()
+                        .map(|&index| index + base),
+                );
+                run_end += 1;
+            }
+            run_start = run_end;
+
+            if first.state.clip != clip {
+                clip = first.state.clip;
+                canvas.set_clip_rect(clip);
+            }
+
+            if first.kind != Kind::Triangles {
+                let points: Vec<shape::Point> = vertices
+                    .iter()
+                    .map(|v| shape::Point::new(v.position.x as i32, v.position.y as i32))
+                    .collect();
+                let c = vertices[0].color;
+                canvas.set_draw_color(pixels::Color::RGBA(c.r, c.g, c.b, c.a));
+                blend::set_draw_blend_mode(canvas, first.state.blend_mode)?;
+                if first.kind == Kind::Lines {
+                    canvas.draw_lines(&points[..])?;
+                } else {
+                    canvas.draw_points(&points[..])?;
+                }
+                continue;
+            }
+
+            // The vertices carry the color, so the texture itself must not be tinted.
+            if first.texture.is_null() {
+                blend::set_draw_blend_mode(canvas, first.state.blend_mode)?;
+            } else {
+                unsafe {
+                    sys::SDL_SetTextureColorMod(first.texture, 255, 255, 255);
+                    sys::SDL_SetTextureAlphaMod(first.texture, 255);
+                }
+                blend::set_raw_texture_blend_mode(first.texture, first.state.blend_mode)?;
+            }
+            geometry::render_geometry_raw(canvas, first.texture, &vertices, &indices)?;
+        }
+
+        self.clear();
+        Ok(())
+    }
+}
+
+/// Store points as vertices, so that they can be kept with everything else.
+fn point_vertices(points: &[shape::Point], color: pixels::Color) -> Vec<RawVertex> {
+    points
+        .iter()
+        .map(|p| RawVertex::new(p.x() as f32, p.y() as f32, color))
+        .collect()
+}
+
+#[cfg(test)]
+fn push_fake(batch: &mut Batch, layer: i32, texture: usize, blend_mode: BlendMode) {
+    let state = State {
+        layer,
+        blend_mode,
+        clip: None,
+    };
+    let vertex = RawVertex::new(0.0, 0.0, pixels::Color::RGB(0, 0, 0));
+    let first_index = batch.indices.len();
+    batch.indices.push(0);
+    batch.push_item(
+        state,
+        Kind::Triangles,
+        texture as *mut sys::SDL_Texture,
+        &[vertex],
+        first_index,
+    );
+}
+
+#[cfg(test)]
+fn push_fakes(batch: &mut Batch) {
+    push_fake(batch, 0, 1, BlendMode::Alpha); // 0
+    push_fake(batch, 0, 2, BlendMode::Alpha); // 1
+    push_fake(batch, 0, 1, BlendMode::Alpha); // 2
+    push_fake(batch, 0, 1, BlendMode::Additive); // 3
+    push_fake(batch, -1, 2, BlendMode::Alpha); // 4
+    push_fake(batch, 5, 1, BlendMode::Alpha); // 5
+    push_fake(batch, 0, 2, BlendMode::Alpha); // 6
+}
+
+#[test]
+fn batch_order() {
+    let mut batch = Batch::new(BatchMode::GroupByImage);
+    push_fakes(&mut batch);
+
+    // lower layers first, then grouped by texture and blend mode in order of first appearance
+    assert_eq!(batch.order(), vec![4, 0, 2, 1, 6, 3, 5]);
+
+    // lines are never moved
+    let state = State {
+        layer: 0,
+        blend_mode: BlendMode::Alpha,
+        clip: None,
+    };
+    batch.push_lines(
+        state,
+        &[shape::Point::new(0, 0)],
+        pixels::Color::RGB(0, 0, 0),
+    );
+    push_fake(&mut batch, 0, 1, BlendMode::Alpha); // 8
+    assert_eq!(batch.order(), vec![4, 0, 2, 8, 1, 6, 3, 7, 5]);
+}
+
+#[test]
+fn batch_keep_order() {
+    let mut batch = Batch::new(BatchMode::KeepOrder);
+    push_fakes(&mut batch);
+    assert_eq!(batch.order(), vec![4, 0, 1, 2, 3, 6, 5]);
+}
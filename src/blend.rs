@@ -0,0 +1,130 @@
+/*!
+ * This blend module holds `BlendMode`. Everything else in it is for internal library use only.
+ *
+ * The sdl2 crate represents `SDL_BlendMode` as a Rust enum, which can't hold the custom modes that
+ * `SDL_ComposeCustomBlendMode` returns. We declare the few functions we need ourselves, passing
+ * blend modes around as plain integers. Custom blend modes require SDL 2.0.6 or newer.
+ */
+
+use std::os::raw::c_int;
+
+extern crate sdl2;
+use sdl2::render;
+use sdl2::sys;
+
+// Values of SDL_BlendMode, SDL_BlendFactor and SDL_BlendOperation from SDL_blendmode.h.
+const BLENDMODE_NONE: c_int = 0x0;
+const BLENDMODE_BLEND: c_int = 0x1;
+const BLENDMODE_ADD: c_int = 0x2;
+const BLENDMODE_MOD: c_int = 0x4;
+
+const BLENDOPERATION_ADD: c_int = 0x1;
+
+const BLENDFACTOR_ONE: c_int = 0x2;
+const BLENDFACTOR_ONE_MINUS_SRC_ALPHA: c_int = 0x6;
+const BLENDFACTOR_DST_COLOR: c_int = 0x7;
+const BLENDFACTOR_DST_ALPHA: c_int = 0x9;
+
+extern "C" {
+    fn SDL_ComposeCustomBlendMode(
+        src_color_factor: c_int,
+        dst_color_factor: c_int,
+        color_operation: c_int,
+        src_alpha_factor: c_int,
+        dst_alpha_factor: c_int,
+        alpha_operation: c_int,
+    ) -> c_int;
+    fn SDL_SetRenderDrawBlendMode(renderer: *mut sys::SDL_Renderer, blend_mode: c_int) -> c_int;
+    fn SDL_SetTextureBlendMode(texture: *mut sys::SDL_Texture, blend_mode: c_int) -> c_int;
+}
+
+/**
+ * BlendMode decides how the colors of whatever is drawn are combined with the colors that are
+ * already on the screen. See `Window::set_blend_mode`.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Overwrite the screen, alpha channel and all.
+    None,
+
+    /// Mix with the screen according to the alpha channel. This is the default.
+    Alpha,
+
+    /// Add to the colors on the screen, so that things can only get brighter. Good for glows,
+    /// fire and lights.
+    Additive,
+
+    /// Multiply the colors on the screen by the colors drawn, ignoring the alpha channel. Good for
+    /// darkening the screen with a light map.
+    Modulate,
+
+    /// Like `Modulate`, but transparent parts leave the screen alone.
+    Multiply,
+
+    /// Like `Alpha`, for images whose colors have already been multiplied by their alpha channel.
+    /// A Canvas that was drawn onto while it was transparent holds exactly that kind of image.
+    Premultiplied,
+}
+
+impl BlendMode {
+    /// Return the SDL_BlendMode value for this mode.
+    fn raw(self) -> c_int {
+        match self {
+            BlendMode::None => BLENDMODE_NONE,
+            BlendMode::Alpha => BLENDMODE_BLEND,
+            BlendMode::Additive => BLENDMODE_ADD,
+            BlendMode::Modulate => BLENDMODE_MOD,
+            BlendMode::Multiply => unsafe {
+                // the same as SDL_BLENDMODE_MUL, which was only added in SDL 2.0.12
+                SDL_ComposeCustomBlendMode(
+                    BLENDFACTOR_DST_COLOR,
+                    BLENDFACTOR_ONE_MINUS_SRC_ALPHA,
+                    BLENDOPERATION_ADD,
+                    BLENDFACTOR_DST_ALPHA,
+                    BLENDFACTOR_ONE_MINUS_SRC_ALPHA,
+                    BLENDOPERATION_ADD,
+                )
+            },
+            BlendMode::Premultiplied => unsafe {
+                SDL_ComposeCustomBlendMode(
+                    BLENDFACTOR_ONE,
+                    BLENDFACTOR_ONE_MINUS_SRC_ALPHA,
+                    BLENDOPERATION_ADD,
+                    BLENDFACTOR_ONE,
+                    BLENDFACTOR_ONE_MINUS_SRC_ALPHA,
+                    BLENDOPERATION_ADD,
+                )
+            },
+        }
+    }
+}
+
+/// Use `mode` for everything drawn on the canvas without a texture.
+pub fn set_draw_blend_mode(
+    canvas: &mut render::Canvas<sdl2::video::Window>,
+    mode: BlendMode,
+) -> Result<(), String> {
+    if unsafe { SDL_SetRenderDrawBlendMode(canvas.raw(), mode.raw()) } != 0 {
+        return Err(sdl2::get_error());
+    }
+    Ok(())
+}
+
+/// Use `mode` whenever `texture` is drawn.
+pub fn set_texture_blend_mode(
+    texture: &mut render::Texture,
+    mode: BlendMode,
+) -> Result<(), String> {
+    set_raw_texture_blend_mode(texture.raw(), mode)
+}
+
+/// Like `set_texture_blend_mode`, for a raw texture pointer.
+pub fn set_raw_texture_blend_mode(
+    texture: *mut sys::SDL_Texture,
+    mode: BlendMode,
+) -> Result<(), String> {
+    if unsafe { SDL_SetTextureBlendMode(texture, mode.raw()) } != 0 {
+        return Err(sdl2::get_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,437 @@
+/*!
+ * Reading AngelCode BMFont descriptors (`.fnt` files), as exported by BMFont, Hiero and others.
+ *
+ * The file format is documented at https://www.angelcode.com/products/bmfont/doc/file_format.html
+ *
+ * All three flavors are read: text, XML and binary (version 3). Only what is needed to print is
+ * kept: the line height, the baseline, the page file names, the characters and the kerning pairs.
+ * Fonts with characters packed into separate color channels are not supported.
+ */
+
+use std::collections::HashMap;
+
+const BLOCK_COMMON: u8 = 2;
+const BLOCK_PAGES: u8 = 3;
+const BLOCK_CHARS: u8 = 4;
+const BLOCK_KERNING: u8 = 5;
+
+/// Where one character is on its page, and how to place it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Char {
+    pub ch: char,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Where the top-left corner of the character goes, relative to the pen position at the top
+    /// of the line.
+    pub offset: (i32, i32),
+    pub advance: i32,
+    pub page: usize,
+}
+
+/// A parsed BMFont descriptor.
+#[derive(Debug, PartialEq)]
+pub struct Document {
+    pub line_height: u32,
+    /// The distance from the top of a line to the baseline.
+    pub base: u32,
+    /// The file names of the page images, in page order.
+    pub pages: Vec<String>,
+    pub chars: Vec<Char>,
+    pub kerning: Vec<(char, char, i32)>,
+}
+
+/// Decode a BMFont descriptor in any of its three flavors.
+pub fn parse(data: &[u8]) -> Result<Document, String> {
+    let doc = if data.starts_with(b"BMF") {
+        parse_binary(data)?
+    } else {
+        let text = std::str::from_utf8(data).map_err(|_| "not a BMFont file".to_string())?;
+        if text.trim_start().starts_with('<') {
+            parse_tags(&xml_tags(text), true)?
+        } else {
+            parse_tags(&text.lines().collect::<Vec<_>>(), false)?
+        }
+    };
+
+    if doc.pages.is_empty() {
+        return Err("BMFont file has no pages".to_string());
+    }
+    if let Some(c) = doc.chars.iter().find(|c| c.page >= doc.pages.len()) {
+        return Err(format!(
+            "BMFont character {:?} is on page {}, which doesn't exist",
+            c.ch, c.page
+        ));
+    }
+    Ok(doc)
+}
+
+/// Return the inside of every XML tag, such as `char id="32" x="0"/`.
+fn xml_tags(text: &str) -> Vec<&str> {
+    text.split('<')
+        .skip(1)
+        .filter_map(|tag| tag.split('>').next())
+        .filter(|tag| !tag.starts_with('?') && !tag.starts_with('!') && !tag.starts_with('/'))
+        .collect()
+}
+
+/// Split a line like `page id=0 file="a b.png"` into its name and its `key=value` attributes.
+fn attributes(line: &str, xml: bool) -> (&str, HashMap<&str, String>) {
+    let line = line.trim().trim_end_matches('/');
+    let name_end = line.find(char::is_whitespace).unwrap_or(line.len());
+    let mut attributes = HashMap::new();
+    let mut rest = line[name_end..].trim_start();
+    while let Some(equals) = rest.find('=') {
+        let key = rest[..equals].trim();
+        rest = &rest[equals + 1..];
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            rest = quoted.get(end + 1..).unwrap_or("");
+            &quoted[..end]
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let value = &rest[..end];
+            rest = &rest[end..];
+            value
+        };
+        let value = if xml {
+            unescape_xml(value)
+        } else {
+            value.to_string()
+        };
+        attributes.insert(key, value);
+        rest = rest.trim_start();
+    }
+    (&line[..name_end], attributes)
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Read the lines of a text descriptor, or the tags of an XML one, which hold the same attributes.
+fn parse_tags(lines: &[&str], xml: bool) -> Result<Document, String> {
+    let mut doc = Document {
+        line_height: 0,
+        base: 0,
+        pages: vec![],
+        chars: vec![],
+        kerning: vec![],
+    };
+    let mut seen_common = false;
+    // pages by id, since nothing says they have to be listed in order
+    let mut pages = HashMap::new();
+
+    for line in lines {
+        let (name, attrs) = attributes(line, xml);
+        let number = |key: &str| -> Result<i64, String> {
+            let value = attrs
+                .get(key)
+                .ok_or_else(|| format!("BMFont {} is missing {}", name, key))?;
+            value
+                .parse()
+                .map_err(|_| format!("BMFont {} has a bad {} {:?}", name, key, value))
+        };
+        match name {
+            "common" => {
+                if attrs.get("packed").is_some_and(|p| p != "0") {
+                    return Err("BMFont files with packed channels are not supported".to_string());
+                }
+                doc.line_height = number("lineHeight")?.max(0) as u32;
+                doc.base = number("base")?.max(0) as u32;
+                seen_common = true;
+            }
+            "page" => {
+                let file = attrs
+                    .get("file")
+                    .ok_or_else(|| "BMFont page is missing file".to_string())?;
+                pages.insert(number("id")?, file.clone());
+            }
+            "char" => {
+                let ch = match to_char(number("id")?) {
+                    Some(ch) => ch,
+                    None => continue,
+                };
+                doc.chars.push(Char {
+                    ch,
+                    x: number("x")?.max(0) as u32,
+                    y: number("y")?.max(0) as u32,
+                    width: number("width")?.max(0) as u32,
+                    height: number("height")?.max(0) as u32,
+                    offset: (number("xoffset")? as i32, number("yoffset")? as i32),
+                    advance: number("xadvance")? as i32,
+                    page: number("page")?.max(0) as usize,
+                });
+            }
+            "kerning" => {
+                if let (Some(first), Some(second)) =
+                    (to_char(number("first")?), to_char(number("second")?))
+                {
+                    doc.kerning.push((first, second, number("amount")? as i32));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if !seen_common {
+        return Err("not a BMFont file".to_string());
+    }
+    for id in 0..pages.len() as i64 {
+        let file = pages
+            .remove(&id)
+            .ok_or_else(|| format!("BMFont page {} is missing", id))?;
+        doc.pages.push(file);
+    }
+    Ok(doc)
+}
+
+/// Return the character with the id, or None for ids like -1 that aren't characters.
+fn to_char(id: i64) -> Option<char> {
+    if id < 0 || id > u32::MAX as i64 {
+        return None;
+    }
+    std::char::from_u32(id as u32)
+}
+
+/// Reads little-endian values out of a byte slice.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.position + count > self.data.len() {
+            return Err("BMFont file ends unexpectedly".to_string());
+        }
+        let bytes = &self.data[self.position..self.position + count];
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn word(&mut self) -> Result<u16, String> {
+        let b = self.bytes(2)?;
+        Ok(b[0] as u16 | (b[1] as u16) << 8)
+    }
+
+    fn short(&mut self) -> Result<i16, String> {
+        Ok(self.word()? as i16)
+    }
+
+    fn dword(&mut self) -> Result<u32, String> {
+        let b = self.bytes(4)?;
+        Ok(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24)
+    }
+}
+
+fn parse_binary(data: &[u8]) -> Result<Document, String> {
+    let mut r = Reader { data, position: 3 };
+    let version = r.byte()?;
+    if version != 3 {
+        return Err(format!("unsupported BMFont version {}", version));
+    }
+
+    let mut doc = Document {
+        line_height: 0,
+        base: 0,
+        pages: vec![],
+        chars: vec![],
+        kerning: vec![],
+    };
+    let mut seen_common = false;
+    while r.position < data.len() {
+        let kind = r.byte()?;
+        let size = r.dword()? as usize;
+        let mut block = Reader {
+            data: r.bytes(size)?,
+            position: 0,
+        };
+        match kind {
+            BLOCK_COMMON => {
+                doc.line_height = block.word()? as u32;
+                doc.base = block.word()? as u32;
+                block.bytes(6)?; // scaleW, scaleH, pages
+                if block.byte()? & 0x80 != 0 {
+                    return Err("BMFont files with packed channels are not supported".to_string());
+                }
+                seen_common = true;
+            }
+            BLOCK_PAGES => {
+                for name in block.data.split(|&b| b == 0).filter(|n| !n.is_empty()) {
+                    doc.pages.push(String::from_utf8_lossy(name).into_owned());
+                }
+            }
+            BLOCK_CHARS => {
+                for _ in 0..size / 20 {
+                    let id = block.dword()?;
+                    let x = block.word()? as u32;
+                    let y = block.word()? as u32;
+                    let width = block.word()? as u32;
+                    let height = block.word()? as u32;
+                    let offset = (block.short()? as i32, block.short()? as i32);
+                    let advance = block.short()? as i32;
+                    let page = block.byte()? as usize;
+                    block.byte()?; // channel
+                    if let Some(ch) = std::char::from_u32(id) {
+                        doc.chars.push(Char {
+                            ch,
+                            x,
+                            y,
+                            width,
+                            height,
+                            offset,
+                            advance,
+                            page,
+                        });
+                    }
+                }
+            }
+            BLOCK_KERNING => {
+                for _ in 0..size / 10 {
+                    let first = std::char::from_u32(block.dword()?);
+                    let second = std::char::from_u32(block.dword()?);
+                    let amount = block.short()? as i32;
+                    if let (Some(first), Some(second)) = (first, second) {
+                        doc.kerning.push((first, second, amount));
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if !seen_common {
+        return Err("BMFont file has no common block".to_string());
+    }
+    Ok(doc)
+}
+
+#[cfg(test)]
+fn test_document() -> Document {
+    Document {
+        line_height: 12,
+        base: 10,
+        pages: vec!["font 0.png".to_string(), "font_1.png".to_string()],
+        chars: vec![
+            Char {
+                ch: 'A',
+                x: 1,
+                y: 2,
+                width: 7,
+                height: 9,
+                offset: (0, 1),
+                advance: 8,
+                page: 0,
+            },
+            Char {
+                ch: 'V',
+                x: 3,
+                y: 4,
+                width: 6,
+                height: 9,
+                offset: (-1, 1),
+                advance: 7,
+                page: 1,
+            },
+        ],
+        kerning: vec![('A', 'V', -2)],
+    }
+}
+
+#[test]
+fn parse_text() {
+    let text = "info face=\"Test\" size=12 bold=0 italic=0 padding=0,0,0,0\r\n\
+                common lineHeight=12 base=10 scaleW=64 scaleH=64 pages=2 packed=0\r\n\
+                page id=1 file=\"font_1.png\"\r\n\
+                page id=0 file=\"font 0.png\"\r\n\
+                chars count=3\r\n\
+                char id=65   x=1  y=2  width=7  height=9  xoffset=0  yoffset=1  xadvance=8  \
+                page=0  chnl=15\r\n\
+                char id=86   x=3  y=4  width=6  height=9  xoffset=-1 yoffset=1  xadvance=7  \
+                page=1  chnl=15\r\n\
+                char id=-1   x=0  y=0  width=0  height=0  xoffset=0  yoffset=0  xadvance=0  \
+                page=0  chnl=15\r\n\
+                kernings count=1\r\n\
+                kerning first=65  second=86  amount=-2\r\n";
+    assert_eq!(parse(text.as_bytes()), Ok(test_document()));
+}
+
+#[test]
+fn parse_xml() {
+    let xml = r#"<?xml version="1.0"?>
+<font>
+  <info face="Test" size="12"/>
+  <common lineHeight="12" base="10" scaleW="64" scaleH="64" pages="2" packed="0"/>
+  <pages>
+    <page id="0" file="font 0.png" />
+    <page id="1" file="font_1.png" />
+  </pages>
+  <chars count="2">
+    <char id="65" x="1" y="2" width="7" height="9" xoffset="0" yoffset="1" xadvance="8" page="0" chnl="15" />
+    <char id="86" x="3" y="4" width="6" height="9" xoffset="-1" yoffset="1" xadvance="7" page="1" chnl="15" />
+  </chars>
+  <kernings count="1">
+    <kerning first="65" second="86" amount="-2" />
+  </kernings>
+</font>
+"#;
+    assert_eq!(parse(xml.as_bytes()), Ok(test_document()));
+}
+
+#[test]
+fn parse_binary_file() {
+    let mut data = b"BMF\x03".to_vec();
+    let mut block = |kind: u8, bytes: Vec<u8>| {
+        data.push(kind);
+        data.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        data.extend(bytes);
+    };
+    block(
+        1,
+        b"\x0c\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00Test\x00".to_vec(),
+    );
+    block(2, vec![12, 0, 10, 0, 64, 0, 64, 0, 2, 0, 0, 0, 0, 0, 0]);
+    block(3, b"font 0.png\x00font_1.png\x00".to_vec());
+    let mut chars = vec![];
+    for &(id, x, y, w, xoffset, advance, page) in &[(65, 1, 2, 7, 0, 8, 0), (86, 3, 4, 6, -1, 7, 1)]
+    {
+        chars.extend_from_slice(&(id as u32).to_le_bytes());
+        for &n in &[x, y, w, 9, xoffset, 1, advance] {
+            chars.extend_from_slice(&(n as i16).to_le_bytes());
+        }
+        chars.extend_from_slice(&[page, 15]);
+    }
+    block(4, chars);
+    let mut kerning = vec![];
+    kerning.extend_from_slice(&65u32.to_le_bytes());
+    kerning.extend_from_slice(&86u32.to_le_bytes());
+    kerning.extend_from_slice(&(-2i16).to_le_bytes());
+    block(5, kerning);
+    assert_eq!(parse(&data), Ok(test_document()));
+
+    // cut off in the middle of a block
+    assert!(parse(&data[..data.len() - 3]).is_err());
+}
+
+#[test]
+fn parse_rejects_garbage() {
+    assert!(parse(b"").is_err());
+    assert!(parse(b"hello world").is_err());
+    assert!(parse(b"BMF\x02").is_err());
+    assert!(parse(b"common lineHeight=12 base=10 packed=1\npage id=0 file=\"a.png\"").is_err());
+    // a character on a page that isn't listed
+    let text = "common lineHeight=12 base=10\npage id=0 file=\"a.png\"\n\
+                char id=65 x=0 y=0 width=1 height=1 xoffset=0 yoffset=0 xadvance=1 page=1";
+    assert!(parse(text.as_bytes()).is_err());
+}
@@ -0,0 +1,315 @@
+use std::fmt;
+use std::str::FromStr;
+
+extern crate sdl2;
+use sdl2::pixels;
+
+/**
+ * Color is an RGBA color with 8 bits per channel.
+ *
+ * Colors can be built from channels, hex strings, HSV or HSL, and parsed from strings like
+ * `"#ff8800"` or `"orange"`, so palettes can be kept in data files:
+ *
+ * ```rust
+ * use simple::Color;
+ *
+ * let sky: Color = "#87ceeb".parse().unwrap();
+ * let dusk = sky.lerp(Color::PURPLE, 0.5);
+ * let fire = Color::from_hsv(30.0, 1.0, 1.0);
+ * assert_eq!(fire, Color::rgb(255, 128, 0));
+ * ```
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const GRAY: Color = Color::rgb(128, 128, 128);
+    pub const RED: Color = Color::rgb(255, 0, 0);
+    pub const GREEN: Color = Color::rgb(0, 255, 0);
+    pub const BLUE: Color = Color::rgb(0, 0, 255);
+    pub const YELLOW: Color = Color::rgb(255, 255, 0);
+    pub const CYAN: Color = Color::rgb(0, 255, 255);
+    pub const MAGENTA: Color = Color::rgb(255, 0, 255);
+    pub const ORANGE: Color = Color::rgb(255, 165, 0);
+    pub const PURPLE: Color = Color::rgb(128, 0, 128);
+    pub const BROWN: Color = Color::rgb(165, 42, 42);
+    pub const PINK: Color = Color::rgb(255, 192, 203);
+
+    /// Build an opaque Color.
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color::rgba(r, g, b, 255)
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+
+    /// Build a Color from channels between 0.0 and 1.0. Values outside of that range are clamped.
+    pub fn from_floats(r: f32, g: f32, b: f32, a: f32) -> Color {
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Color::rgba(channel(r), channel(g), channel(b), channel(a))
+    }
+
+    /// Return the channels as values between 0.0 and 1.0.
+    pub fn to_floats(self) -> (f32, f32, f32, f32) {
+        let channel = |c: u8| c as f32 / 255.0;
+        (
+            channel(self.r),
+            channel(self.g),
+            channel(self.b),
+            channel(self.a),
+        )
+    }
+
+    /// Parse a hex color: `"#rgb"`, `"#rgba"`, `"#rrggbb"` or `"#rrggbbaa"`. The `#` is
+    /// optional.
+    pub fn from_hex(hex: &str) -> Result<Color, String> {
+        let digits = hex.trim_start_matches('#');
+        let invalid = || format!("{:?} is not a hex color", hex);
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+
+        // Short forms repeat each digit, so "f80" means "ff8800".
+        let (width, scale) = match digits.len() {
+            3 | 4 => (1, 17),
+            6 | 8 => (2, 1),
+            _ => return Err(invalid()),
+        };
+        let channels: Vec<u8> = (0..digits.len() / width)
+            .map(|i| {
+                let value = u8::from_str_radix(&digits[i * width..(i + 1) * width], 16).unwrap();
+                value * scale
+            })
+            .collect();
+        let alpha = channels.get(3).cloned().unwrap_or(255);
+        Ok(Color::rgba(channels[0], channels[1], channels[2], alpha))
+    }
+
+    /// Return the color as a `"#rrggbbaa"` string.
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, self.a)
+    }
+
+    /// Look up one of the named constants, like `"orange"`, ignoring case.
+    pub fn from_name(name: &str) -> Option<Color> {
+        let color = match &*name.to_lowercase() {
+            "transparent" => Color::TRANSPARENT,
+            "black" => Color::BLACK,
+            "white" => Color::WHITE,
+            "gray" | "grey" => Color::GRAY,
+            "red" => Color::RED,
+            "green" => Color::GREEN,
+            "blue" => Color::BLUE,
+            "yellow" => Color::YELLOW,
+            "cyan" => Color::CYAN,
+            "magenta" => Color::MAGENTA,
+            "orange" => Color::ORANGE,
+            "purple" => Color::PURPLE,
+            "brown" => Color::BROWN,
+            "pink" => Color::PINK,
+            _ => return None,
+        };
+        Some(color)
+    }
+
+    /// Build an opaque Color from a hue in degrees and a saturation and value between 0.0 and
+    /// 1.0.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Color {
+        let chroma = value * saturation;
+        let (r, g, b) = hue_to_rgb(hue, chroma);
+        let m = value - chroma;
+        Color::from_floats(r + m, g + m, b + m, 1.0)
+    }
+
+    /// Build an opaque Color from a hue in degrees and a saturation and lightness between 0.0
+    /// and 1.0.
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Color {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        let (r, g, b) = hue_to_rgb(hue, chroma);
+        let m = lightness - chroma / 2.0;
+        Color::from_floats(r + m, g + m, b + m, 1.0)
+    }
+
+    /// Return the hue in degrees and the saturation and value between 0.0 and 1.0.
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let (r, g, b, _) = self.to_floats();
+        let max = r.max(g).max(b);
+        let chroma = max - r.min(g).min(b);
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+        (rgb_to_hue(r, g, b, max, chroma), saturation, max)
+    }
+
+    /// Return the same color with a different alpha.
+    pub fn with_alpha(self, a: u8) -> Color {
+        Color { a, ..self }
+    }
+
+    /// Blend towards `other`: 0.0 returns `self`, 1.0 returns `other`.
+    pub fn lerp(self, other: Color, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Color::rgba(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+            mix(self.a, other.a),
+        )
+    }
+
+    /// Multiply the red, green and blue channels by alpha, for use with
+    /// `BlendMode::Premultiplied`.
+    pub fn premultiply(self) -> Color {
+        let mul = |c: u8| ((c as u32 * self.a as u32 + 127) / 255) as u8;
+        Color::rgba(mul(self.r), mul(self.g), mul(self.b), self.a)
+    }
+
+    /// Multiply two colors together channel by channel, the same way a draw color tints an image.
+    pub fn modulate(self, other: Color) -> Color {
+        let mul = |a: u8, b: u8| ((a as u32 * b as u32 + 127) / 255) as u8;
+        Color::rgba(
+            mul(self.r, other.r),
+            mul(self.g, other.g),
+            mul(self.b, other.b),
+            mul(self.a, other.a),
+        )
+    }
+}
+
+/// Return the red, green and blue parts of a color with the given hue and chroma, before the
+/// lightness is added.
+fn hue_to_rgb(hue: f32, chroma: f32) -> (f32, f32, f32) {
+    let h = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    }
+}
+
+fn rgb_to_hue(r: f32, g: f32, b: f32, max: f32, chroma: f32) -> f32 {
+    if chroma == 0.0 {
+        return 0.0;
+    }
+    let h = if max == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    h * 60.0
+}
+
+impl Default for Color {
+    /// White, which leaves whatever it is applied to unchanged.
+    fn default() -> Self {
+        Color::WHITE
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    /// Parse either a hex color (see `from_hex`) or a color name (see `from_name`).
+    fn from_str(s: &str) -> Result<Color, String> {
+        let s = s.trim();
+        match Color::from_name(s) {
+            Some(color) => Ok(color),
+            None => Color::from_hex(s),
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+impl From<(u8, u8, u8)> for Color {
+    fn from((r, g, b): (u8, u8, u8)) -> Color {
+        Color::rgb(r, g, b)
+    }
+}
+
+impl From<(u8, u8, u8, u8)> for Color {
+    fn from((r, g, b, a): (u8, u8, u8, u8)) -> Color {
+        Color::rgba(r, g, b, a)
+    }
+}
+
+impl From<Color> for pixels::Color {
+    fn from(c: Color) -> pixels::Color {
+        pixels::Color::RGBA(c.r, c.g, c.b, c.a)
+    }
+}
+
+impl From<pixels::Color> for Color {
+    fn from(c: pixels::Color) -> Color {
+        Color::rgba(c.r, c.g, c.b, c.a)
+    }
+}
+
+#[test]
+fn color_hex() {
+    assert_eq!(Color::from_hex("#ff8800"), Ok(Color::rgb(255, 136, 0)));
+    assert_eq!(
+        Color::from_hex("ff880080"),
+        Ok(Color::rgba(255, 136, 0, 128))
+    );
+    assert_eq!(Color::from_hex("#f80"), Ok(Color::rgb(255, 136, 0)));
+    assert_eq!(Color::from_hex("#f808"), Ok(Color::rgba(255, 136, 0, 136)));
+    assert!(Color::from_hex("#ff88").is_ok());
+    assert!(Color::from_hex("#ff880").is_err());
+    assert!(Color::from_hex("#gg8800").is_err());
+    assert!(Color::from_hex("").is_err());
+
+    assert_eq!(Color::rgba(1, 2, 254, 255).to_hex(), "#0102feff");
+    assert_eq!("Orange".parse(), Ok(Color::ORANGE));
+    assert_eq!(" #000 ".parse(), Ok(Color::BLACK));
+    assert!("not a color".parse::<Color>().is_err());
+}
+
+#[test]
+fn color_hsv_hsl() {
+    assert_eq!(Color::from_hsv(0.0, 1.0, 1.0), Color::RED);
+    assert_eq!(Color::from_hsv(120.0, 1.0, 1.0), Color::GREEN);
+    assert_eq!(Color::from_hsv(-120.0, 1.0, 1.0), Color::BLUE);
+    assert_eq!(Color::from_hsv(200.0, 0.0, 0.5), Color::GRAY);
+    assert_eq!(Color::from_hsl(60.0, 1.0, 0.5), Color::YELLOW);
+    assert_eq!(Color::from_hsl(300.0, 1.0, 1.0), Color::WHITE);
+
+    let (h, s, v) = Color::rgb(255, 128, 0).to_hsv();
+    assert!((h - 30.0).abs() < 0.5 && s == 1.0 && v == 1.0);
+}
+
+#[test]
+fn color_mixing() {
+    assert_eq!(
+        Color::BLACK.lerp(Color::WHITE, 0.5),
+        Color::rgb(128, 128, 128)
+    );
+    assert_eq!(Color::RED.lerp(Color::BLUE, 2.0), Color::BLUE);
+    assert_eq!(
+        Color::rgba(255, 100, 0, 128).premultiply(),
+        Color::rgba(128, 50, 0, 128)
+    );
+    assert_eq!(
+        Color::from_floats(1.0, 0.5, -1.0, 1.0),
+        Color::rgb(255, 128, 0)
+    );
+    assert_eq!(Color::ORANGE.modulate(Color::WHITE), Color::ORANGE);
+}
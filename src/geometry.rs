@@ -0,0 +1,516 @@
+/*!
+ * This geometry module holds `Vertex`. Everything else in it is for internal library use only.
+ *
+ * `SDL_RenderGeometry` draws arbitrary triangles with a color at every vertex, which is how we draw
+ * anything that isn't a plain rectangle or a plain texture copy. It was added in SDL 2.0.18, which
+ * is newer than the version of the sdl2 crate that we depend on. Linking to it would stop programs
+ * from starting at all on an older SDL, so we look it up when it is first needed instead.
+ *
+ * Without it, triangles without a texture are filled one row of pixels at a time, and textured
+ * triangles that make up upright rectangles, like sprites and glyphs, are copied with
+ * `SDL_RenderCopyEx`. Anything else returns an error asking for a newer SDL.
+ */
+
+use std::mem;
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+extern crate sdl2;
+use sdl2::pixels;
+use sdl2::render;
+use sdl2::sys;
+
+use color::Color;
+use shape;
+
+/**
+ * Vertex is one corner of a triangle drawn with `Window::draw_mesh`.
+ *
+ * `u` and `v` pick the point of the image that the vertex shows, from (0, 0) at the top-left corner
+ * of the image to (1, 1) at the bottom-right corner. They are ignored when no image is drawn.
+ */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Vertex {
+    pub x: f32,
+    pub y: f32,
+    pub color: Color,
+    pub u: f32,
+    pub v: f32,
+}
+
+impl Vertex {
+    /// Build a vertex at (x, y) with the given color.
+    pub fn new(x: f32, y: f32, color: Color) -> Self {
+        Vertex::textured(x, y, color, 0.0, 0.0)
+    }
+
+    /// Build a vertex at (x, y) that shows the image at (`u`, `v`), tinted by `color`.
+    pub fn textured(x: f32, y: f32, color: Color, u: f32, v: f32) -> Self {
+        Vertex { x, y, color, u, v }
+    }
+}
+
+/// Mirror of `SDL_FPoint`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Mirror of `SDL_Vertex`.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct RawVertex {
+    pub position: FPoint,
+    pub color: sys::SDL_Color,
+    pub tex_coord: FPoint,
+}
+
+impl RawVertex {
+    /// Build an untextured vertex.
+    pub fn new(x: f32, y: f32, color: pixels::Color) -> Self {
+        RawVertex::textured(x, y, color, 0.0, 0.0)
+    }
+
+    /// Build a vertex that samples the texture at (`u`, `v`), where both range from 0 to 1.
+    pub fn textured(x: f32, y: f32, color: pixels::Color, u: f32, v: f32) -> Self {
+        RawVertex {
+            position: FPoint { x, y },
+            color: sys::SDL_Color {
+                r: color.r,
+                g: color.g,
+                b: color.b,
+                a: color.a,
+            },
+            tex_coord: FPoint { x: u, y: v },
+        }
+    }
+}
+
+/// The signature of `SDL_RenderGeometry`.
+type RenderGeometry = unsafe extern "C" fn(
+    renderer: *mut sys::SDL_Renderer,
+    texture: *mut sys::SDL_Texture,
+    vertices: *const RawVertex,
+    num_vertices: c_int,
+    indices: *const c_int,
+    num_indices: c_int,
+) -> c_int;
+
+/// The address of `SDL_RenderGeometry`, once it has been looked up.
+static RENDER_GEOMETRY: AtomicUsize = AtomicUsize::new(NOT_LOOKED_UP);
+const NOT_LOOKED_UP: usize = 0;
+const NOT_FOUND: usize = 1;
+
+/// The library to look `SDL_RenderGeometry` up in. On Windows it has to be named. Elsewhere, a
+/// null name searches the program and every library it was started with, SDL included.
+#[cfg(windows)]
+const SDL_LIBRARY: *const c_char = b"SDL2.dll\0" as *const u8 as *const c_char;
+#[cfg(not(windows))]
+const SDL_LIBRARY: *const c_char = ptr::null();
+
+/// Return `SDL_RenderGeometry`, or None if the SDL that was loaded is older than 2.0.18.
+fn render_geometry_fn() -> Option<RenderGeometry> {
+    let mut address = RENDER_GEOMETRY.load(Ordering::Relaxed);
+    if address == NOT_LOOKED_UP {
+        let function: *mut c_void = unsafe {
+            let library = sys::SDL_LoadObject(SDL_LIBRARY);
+            if library.is_null() {
+                ptr::null_mut()
+            } else {
+                let name = b"SDL_RenderGeometry\0";
+                sys::SDL_LoadFunction(library, name.as_ptr() as *const c_char)
+            }
+        };
+        address = if function.is_null() {
+            NOT_FOUND
+        } else {
+            function as usize
+        };
+        RENDER_GEOMETRY.store(address, Ordering::Relaxed);
+    }
+    if address == NOT_FOUND {
+        None
+    } else {
+        Some(unsafe { mem::transmute::<usize, RenderGeometry>(address) })
+    }
+}
+
+/// Draw a list of triangles onto the canvas. If `indices` is empty, every three consecutive
+/// vertices make up one triangle.
+pub fn render_geometry(
+    canvas: &mut render::Canvas<sdl2::video::Window>,
+    texture: Option<&render::Texture>,
+    vertices: &[RawVertex],
+    indices: &[i32],
+) -> Result<(), String> {
+    let texture = match texture {
+        Some(t) => t.raw(),
+        None => ptr::null_mut(),
+    };
+    render_geometry_raw(canvas, texture, vertices, indices)
+}
+
+/// Like `render_geometry`, for a raw texture pointer, which may be null.
+pub fn render_geometry_raw(
+    canvas: &mut render::Canvas<sdl2::video::Window>,
+    texture: *mut sys::SDL_Texture,
+    vertices: &[RawVertex],
+    indices: &[i32],
+) -> Result<(), String> {
+    if vertices.is_empty() {
+        return Ok(());
+    }
+    let render_geometry = match render_geometry_fn() {
+        Some(f) => f,
+        None if texture.is_null() => return fill_spans(canvas, vertices, indices),
+        None => return copy_quads(canvas, texture, vertices, indices),
+    };
+    let ret = unsafe {
+        render_geometry(
+            canvas.raw(),
+            texture,
+            vertices.as_ptr(),
+            vertices.len() as c_int,
+            if indices.is_empty() {
+                ptr::null()
+            } else {
+                indices.as_ptr()
+            },
+            indices.len() as c_int,
+        )
+    };
+    if ret != 0 {
+        Err(sdl2::get_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Return the channels of the color, so that colors can be compared.
+fn rgba(color: sys::SDL_Color) -> (u8, u8, u8, u8) {
+    (color.r, color.g, color.b, color.a)
+}
+
+/// Return the vertex indices of every triangle, the way `render_geometry` reads them.
+fn triangles(vertices: &[RawVertex], indices: &[i32]) -> Vec<[usize; 3]> {
+    if indices.is_empty() {
+        (0..vertices.len() / 3)
+            .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+            .collect()
+    } else {
+        indices
+            .chunks(3)
+            .filter(|t| t.len() == 3)
+            .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+            .collect()
+    }
+}
+
+/// A run of pixels on one row that all get the same color.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Span {
+    x: i32,
+    y: i32,
+    width: u32,
+    color: (u8, u8, u8, u8),
+}
+
+/// Return the pixels covered by the triangle, as runs of the same color. A pixel is covered when
+/// its center is, and its color is blended from the vertex colors at its center.
+fn triangle_spans(a: &RawVertex, b: &RawVertex, c: &RawVertex) -> Vec<Span> {
+    let (p, q, r) = (a.position, b.position, c.position);
+    let area = (q.x - p.x) * (r.y - p.y) - (r.x - p.x) * (q.y - p.y);
+    if area == 0.0 {
+        return vec![];
+    }
+    let color_at = |x: f32, y: f32| {
+        // How much of each vertex's color is used at (x, y).
+        let wa = ((q.x - x) * (r.y - y) - (r.x - x) * (q.y - y)) / area;
+        let wb = ((r.x - x) * (p.y - y) - (p.x - x) * (r.y - y)) / area;
+        let wc = 1.0 - wa - wb;
+        let mix = |ca: u8, cb: u8, cc: u8| {
+            let value = wa * ca as f32 + wb * cb as f32 + wc * cc as f32;
+            value.round().clamp(0.0, 255.0) as u8
+        };
+        let (ca, cb, cc) = (a.color, b.color, c.color);
+        (
+            mix(ca.r, cb.r, cc.r),
+            mix(ca.g, cb.g, cc.g),
+            mix(ca.b, cb.b, cc.b),
+            mix(ca.a, cb.a, cc.a),
+        )
+    };
+
+    let top = p.y.min(q.y).min(r.y);
+    let bottom = p.y.max(q.y).max(r.y);
+    let mut spans = vec![];
+    let mut y = (top - 0.5).ceil() as i32;
+    while (y as f32 + 0.5) < bottom {
+        let center_y = y as f32 + 0.5;
+        let (mut left, mut right) = (f32::MAX, f32::MIN);
+        for &(from, to) in &[(p, q), (q, r), (r, p)] {
+            let (upper, lower) = if from.y < to.y {
+                (from, to)
+            } else {
+                (to, from)
+            };
+            if upper.y <= center_y && center_y < lower.y {
+                let x = upper.x + (lower.x - upper.x) * (center_y - upper.y) / (lower.y - upper.y);
+                left = left.min(x);
+                right = right.max(x);
+            }
+        }
+        let mut x = (left - 0.5).ceil() as i32;
+        while (x as f32 + 0.5) < right {
+            let color = color_at(x as f32 + 0.5, center_y);
+            match spans.last_mut() {
+                Some(&mut Span {
+                    x: span_x,
+                    y: span_y,
+                    ref mut width,
+                    color: span_color,
+                }) if span_y == y && span_x + *width as i32 == x && span_color == color => {
+                    *width += 1
+                }
+                _ => spans.push(Span {
+                    x,
+                    y,
+                    width: 1,
+                    color,
+                }),
+            }
+            x += 1;
+        }
+        y += 1;
+    }
+    spans
+}
+
+/// Fill untextured triangles without `SDL_RenderGeometry`, with the canvas's blend mode.
+fn fill_spans(
+    canvas: &mut render::Canvas<sdl2::video::Window>,
+    vertices: &[RawVertex],
+    indices: &[i32],
+) -> Result<(), String> {
+    let previous = canvas.draw_color();
+    for [a, b, c] in triangles(vertices, indices) {
+        for span in triangle_spans(&vertices[a], &vertices[b], &vertices[c]) {
+            let (r, g, b, a) = span.color;
+            canvas.set_draw_color(pixels::Color::RGBA(r, g, b, a));
+            canvas.fill_rect(shape::Rect::new(span.x, span.y, span.width, 1))?;
+        }
+    }
+    canvas.set_draw_color(previous);
+    Ok(())
+}
+
+/// A textured triangle pair that can be drawn as a plain copy of part of the texture.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct QuadCopy {
+    source: shape::Rect,
+    dest: shape::Rect,
+    flip_horizontal: bool,
+    flip_vertical: bool,
+    color: (u8, u8, u8, u8),
+}
+
+/// If the triangles are pairs that make up upright rectangles, laid out the way `QUAD_INDICES`
+/// does it, return them as copies from a texture of the given size.
+fn quad_copies(
+    vertices: &[RawVertex],
+    indices: &[i32],
+    texture_width: u32,
+    texture_height: u32,
+) -> Option<Vec<QuadCopy>> {
+    let mut copies = vec![];
+    for pair in triangles(vertices, indices).chunks(2) {
+        if pair.len() != 2 {
+            return None;
+        }
+        // The four corners must come one after another, in the order of QUAD_INDICES.
+        let first = pair[0][0];
+        if pair != [[first, first + 1, first + 2], [first, first + 2, first + 3]]
+            || first + 3 >= vertices.len()
+        {
+            return None;
+        }
+        let (top_left, top_right) = (&vertices[first], &vertices[first + 1]);
+        let (bottom_right, bottom_left) = (&vertices[first + 2], &vertices[first + 3]);
+        let upright = top_left.position.y == top_right.position.y
+            && top_right.position.x == bottom_right.position.x
+            && bottom_right.position.y == bottom_left.position.y
+            && bottom_left.position.x == top_left.position.x;
+        let same_color = [top_right, bottom_right, bottom_left]
+            .iter()
+            .all(|v| rgba(v.color) == rgba(top_left.color));
+        if !upright || !same_color {
+            return None;
+        }
+
+        let (x0, x1) = (top_left.position.x.round(), bottom_right.position.x.round());
+        let (y0, y1) = (top_left.position.y.round(), bottom_right.position.y.round());
+        let (u0, u1) = (top_left.tex_coord.x, bottom_right.tex_coord.x);
+        let (v0, v1) = (top_left.tex_coord.y, bottom_right.tex_coord.y);
+        let to_texels = |from: f32, to: f32, size: u32| {
+            let (from, to) = ((from * size as f32).round(), (to * size as f32).round());
+            (from.min(to) as i32, (from - to).abs() as u32)
+        };
+        let (source_x, source_width) = to_texels(u0, u1, texture_width);
+        let (source_y, source_height) = to_texels(v0, v1, texture_height);
+        if x0 == x1 || y0 == y1 || source_width == 0 || source_height == 0 {
+            continue;
+        }
+        copies.push(QuadCopy {
+            source: shape::Rect::new(source_x, source_y, source_width, source_height),
+            dest: shape::Rect::new(
+                x0.min(x1) as i32,
+                y0.min(y1) as i32,
+                (x1 - x0).abs() as u32,
+                (y1 - y0).abs() as u32,
+            ),
+            flip_horizontal: (x0 < x1) != (u0 < u1),
+            flip_vertical: (y0 < y1) != (v0 < v1),
+            color: rgba(top_left.color),
+        });
+    }
+    Some(copies)
+}
+
+/// Draw textured triangles without `SDL_RenderGeometry`, if they are upright rectangles. The
+/// texture's color and alpha mod are overwritten.
+fn copy_quads(
+    canvas: &mut render::Canvas<sdl2::video::Window>,
+    texture: *mut sys::SDL_Texture,
+    vertices: &[RawVertex],
+    indices: &[i32],
+) -> Result<(), String> {
+    let (mut width, mut height) = (0, 0);
+    unsafe {
+        sys::SDL_QueryTexture(
+            texture,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            &mut width,
+            &mut height,
+        );
+    }
+    let copies = match quad_copies(vertices, indices, width as u32, height as u32) {
+        Some(copies) => copies,
+        None => {
+            return Err(
+                "drawing rotated, skewed or stretched images needs SDL 2.0.18 or newer".to_string(),
+            )
+        }
+    };
+    for copy in copies {
+        let flip = match (copy.flip_horizontal, copy.flip_vertical) {
+            (false, false) => sys::SDL_RendererFlip::SDL_FLIP_NONE,
+            (true, false) => sys::SDL_RendererFlip::SDL_FLIP_HORIZONTAL,
+            (false, true) => sys::SDL_RendererFlip::SDL_FLIP_VERTICAL,
+            (true, true) => unsafe {
+                mem::transmute::<u32, sys::SDL_RendererFlip>(
+                    sys::SDL_RendererFlip::SDL_FLIP_HORIZONTAL as u32
+                        | sys::SDL_RendererFlip::SDL_FLIP_VERTICAL as u32,
+                )
+            },
+        };
+        let ret = unsafe {
+            let (r, g, b, a) = copy.color;
+            sys::SDL_SetTextureColorMod(texture, r, g, b);
+            sys::SDL_SetTextureAlphaMod(texture, a);
+            sys::SDL_RenderCopyEx(
+                canvas.raw(),
+                texture,
+                copy.source.raw(),
+                copy.dest.raw(),
+                0.0,
+                ptr::null(),
+                flip,
+            )
+        };
+        if ret != 0 {
+            return Err(sdl2::get_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+fn quad(left: f32, top: f32, right: f32, bottom: f32, uv: [f32; 4]) -> Vec<RawVertex> {
+    let white = pixels::Color::RGBA(255, 255, 255, 255);
+    vec![
+        RawVertex::textured(left, top, white, uv[0], uv[1]),
+        RawVertex::textured(right, top, white, uv[2], uv[1]),
+        RawVertex::textured(right, bottom, white, uv[2], uv[3]),
+        RawVertex::textured(left, bottom, white, uv[0], uv[3]),
+    ]
+}
+
+#[test]
+fn spans_cover_pixel_centers() {
+    let red = pixels::Color::RGBA(255, 0, 0, 255);
+    let square = [
+        RawVertex::new(1.0, 1.0, red),
+        RawVertex::new(4.0, 1.0, red),
+        RawVertex::new(4.0, 3.0, red),
+        RawVertex::new(1.0, 3.0, red),
+    ];
+    let mut spans = vec![];
+    for [a, b, c] in triangles(&square, &[0, 1, 2, 0, 2, 3]) {
+        spans.extend(triangle_spans(&square[a], &square[b], &square[c]));
+    }
+    // Every pixel of the 3x2 square is covered exactly once.
+    let mut pixels = vec![];
+    for span in &spans {
+        assert_eq!(span.color, (255, 0, 0, 255));
+        pixels.extend((0..span.width as i32).map(|i| (span.x + i, span.y)));
+    }
+    pixels.sort();
+    assert_eq!(pixels, vec![(1, 1), (1, 2), (2, 1), (2, 2), (3, 1), (3, 2)]);
+
+    // Colors are blended across the triangle, one span per color.
+    let black = pixels::Color::RGBA(0, 0, 0, 255);
+    let white = pixels::Color::RGBA(255, 255, 255, 255);
+    let spans = triangle_spans(
+        &RawVertex::new(0.0, 0.0, black),
+        &RawVertex::new(4.0, 0.0, white),
+        &RawVertex::new(0.0, 4.0, black),
+    );
+    assert_eq!(spans[0].x, 0);
+    assert_eq!(spans[0].y, 0);
+    assert_eq!(spans.iter().filter(|s| s.y == 0).count(), 3);
+    assert!(spans[0].color.0 < spans[1].color.0);
+
+    let flat = triangle_spans(
+        &RawVertex::new(0.0, 0.0, red),
+        &RawVertex::new(2.0, 2.0, red),
+        &RawVertex::new(4.0, 4.0, red),
+    );
+    assert_eq!(flat, vec![]);
+}
+
+#[test]
+fn quads_become_copies() {
+    let mut vertices = quad(10.0, 20.0, 42.0, 36.0, [0.0, 0.5, 0.5, 1.0]);
+    // mirrored horizontally
+    vertices.extend(quad(0.0, 0.0, 16.0, 16.0, [1.0, 0.0, 0.5, 0.5]));
+    let indices = [0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7];
+    let copies = quad_copies(&vertices, &indices, 64, 32).unwrap();
+    assert_eq!(copies.len(), 2);
+    assert_eq!(copies[0].source, shape::Rect::new(0, 16, 32, 16));
+    assert_eq!(copies[0].dest, shape::Rect::new(10, 20, 32, 16));
+    assert!(!copies[0].flip_horizontal && !copies[0].flip_vertical);
+    assert_eq!(copies[1].source, shape::Rect::new(32, 0, 32, 16));
+    assert_eq!(copies[1].dest, shape::Rect::new(0, 0, 16, 16));
+    assert!(copies[1].flip_horizontal && !copies[1].flip_vertical);
+
+    // A quad turned on its side can't be copied.
+    let mut turned = quad(0.0, 0.0, 16.0, 16.0, [0.0, 0.0, 1.0, 1.0]);
+    turned[1].position = FPoint { x: 16.0, y: 4.0 };
+    assert_eq!(quad_copies(&turned, &[0, 1, 2, 0, 2, 3], 16, 16), None);
+    // So can't triangles that aren't in pairs.
+    assert_eq!(quad_copies(&vertices[..4], &[0, 1, 2], 64, 32), None);
+}
@@ -0,0 +1,58 @@
+use color::Color;
+
+/**
+ * Gradient describes how a fill blends between two colors across a shape.
+ *
+ * Gradients are always stretched to the bounding box of the shape being filled, so the same
+ * Gradient can be reused for shapes of any size. Like every other draw operation, the Window's
+ * draw color is applied on top of the gradient.
+ */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Gradient {
+    /// Blend from the first color at the left edge to the second color at the right edge.
+    Horizontal(Color, Color),
+
+    /// Blend from the first color at the top edge to the second color at the bottom edge.
+    Vertical(Color, Color),
+
+    /// Blend from the first color at the center to the second color at the edges.
+    Radial(Color, Color),
+}
+
+impl Gradient {
+    /// Return the color of the gradient at (x, y) when it is stretched over the box with its
+    /// top-left corner at (left, top) and the given width and height.
+    pub fn color_at(&self, left: f32, top: f32, width: f32, height: f32, x: f32, y: f32) -> Color {
+        let (from, to, t) = match *self {
+            Gradient::Horizontal(from, to) => (from, to, (x - left) / width),
+            Gradient::Vertical(from, to) => (from, to, (y - top) / height),
+            Gradient::Radial(from, to) => {
+                let dx = (x - (left + width / 2.0)) / (width / 2.0);
+                let dy = (y - (top + height / 2.0)) / (height / 2.0);
+                (from, to, (dx * dx + dy * dy).sqrt())
+            }
+        };
+        from.lerp(to, if t.is_finite() { t } else { 0.0 })
+    }
+}
+
+#[test]
+fn gradient_color_at() {
+    let black = Color::BLACK;
+    let white = Color::WHITE;
+
+    let g = Gradient::Horizontal(black, white);
+    assert_eq!(g.color_at(10.0, 0.0, 100.0, 5.0, 10.0, 3.0), black);
+    assert_eq!(g.color_at(10.0, 0.0, 100.0, 5.0, 110.0, 3.0), white);
+    assert_eq!(g.color_at(10.0, 0.0, 100.0, 5.0, 60.0, 3.0), Color::GRAY);
+
+    let g = Gradient::Vertical(black, white);
+    assert_eq!(g.color_at(0.0, 0.0, 10.0, 10.0, 3.0, 0.0), black);
+    assert_eq!(g.color_at(0.0, 0.0, 10.0, 10.0, 3.0, 10.0), white);
+
+    // radial gradients reach their outer color at the edge and stay there in the corners
+    let g = Gradient::Radial(white, black);
+    assert_eq!(g.color_at(0.0, 0.0, 10.0, 10.0, 5.0, 5.0), white);
+    assert_eq!(g.color_at(0.0, 0.0, 10.0, 10.0, 10.0, 5.0), black);
+    assert_eq!(g.color_at(0.0, 0.0, 10.0, 10.0, 0.0, 0.0), black);
+}
@@ -5,13 +5,25 @@
 //!
 //! Homepage: https://github.com/alexandercampbell/simple
 
+extern crate miniz_oxide;
 extern crate rand;
 extern crate sdl2;
+extern crate serde_json;
 
 // Re-export some of the symbols from the other modules.
+pub use animation::{Animation, AnimationEvent, PlayMode};
+pub use aseprite::AsepriteFile;
+pub use batch::BatchMode;
+pub use blend::BlendMode;
+pub use color::Color;
 pub use event::Event;
-pub use shape::{Point, Polygon, Rect};
-pub use window::{Font, Image, Window};
+pub use geometry::Vertex;
+pub use gradient::Gradient;
+pub use shape::{CornerRadii, Point, Polygon, Rect};
+pub use sprite::{Sprite, SpriteKey, SpriteSheet};
+pub use text::{Align, PrintOptions, TextOptions, VerticalAlign};
+pub use transform::Transform;
+pub use window::{Canvas, DrawOptions, Font, Image, MarkupStyle, ScaleMode, Text, Window};
 
 // rustdoc has some bugs right now and the below code works around this. Rust issue link:
 // https://github.com/rust-lang/rust/issues/24305
@@ -21,7 +33,20 @@ pub use window::{Font, Image, Window};
 pub use event::Key;
 pub use event::MouseButton;
 
+mod animation;
+mod aseprite;
+mod batch;
+mod blend;
+mod bmfont;
+mod color;
 mod event;
+mod geometry;
+mod gradient;
+mod markup;
 mod shape;
+mod sprite;
+mod text;
+mod transform;
+mod truetype;
 mod util;
 mod window;
@@ -0,0 +1,169 @@
+/*!
+ * Parsing the markup that `Window::print_markup` understands.
+ *
+ * See that function for the tags. Tags of the same kind nest, so
+ * `[color=red]a[color=blue]b[/color]c[/color]` prints "c" in red.
+ */
+
+use color::Color;
+
+/// One piece of parsed markup.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Piece {
+    /// Text in one color and font. `None` means the Window's own color or font. Text never holds
+    /// a `\n`; line breaks are pieces of their own.
+    Text {
+        text: String,
+        color: Option<Color>,
+        font: Option<String>,
+    },
+    /// The image with this name.
+    Image(String),
+    Newline,
+}
+
+/// Split the markup into pieces, or explain what is wrong with it.
+pub fn parse(markup: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = vec![];
+    let mut colors: Vec<Color> = vec![];
+    let mut fonts: Vec<String> = vec![];
+    let mut text = String::new();
+
+    let mut rest = markup;
+    while let Some(open) = rest.find(['[', '\n']) {
+        if rest[open..].starts_with('\n') {
+            // Windows line endings end lines too.
+            text.push_str(rest[..open].trim_end_matches('\r'));
+            push_text(&mut pieces, &mut text, &colors, &fonts);
+            pieces.push(Piece::Newline);
+            rest = &rest[open + 1..];
+            continue;
+        }
+        text.push_str(&rest[..open]);
+        if rest[open..].starts_with("[[") {
+            text.push('[');
+            rest = &rest[open + 2..];
+            continue;
+        }
+
+        let close = match rest[open..].find(']') {
+            Some(close) => open + close,
+            None => return Err(format!("markup tag {:?} is never closed", &rest[open..])),
+        };
+        let tag = &rest[open + 1..close];
+        rest = &rest[close + 1..];
+        push_text(&mut pieces, &mut text, &colors, &fonts);
+
+        let (name, value) = match tag.find('=') {
+            Some(equals) => (tag[..equals].trim(), Some(tag[equals + 1..].trim())),
+            None => (tag.trim(), None),
+        };
+        match (name, value) {
+            ("color", Some(value)) => colors.push(value.parse()?),
+            ("font", Some(value)) => fonts.push(value.to_string()),
+            ("img", Some(value)) => pieces.push(Piece::Image(value.to_string())),
+            ("/color", None) if colors.pop().is_some() => (),
+            ("/font", None) if fonts.pop().is_some() => (),
+            ("/color", None) | ("/font", None) => {
+                return Err(format!("markup tag [{}] has nothing to close", tag));
+            }
+            _ => return Err(format!("unknown markup tag [{}]", tag)),
+        }
+    }
+    text.push_str(rest);
+    push_text(&mut pieces, &mut text, &colors, &fonts);
+    Ok(pieces)
+}
+
+/// Add the text collected so far as a piece, unless there is none, and start collecting again.
+fn push_text(pieces: &mut Vec<Piece>, text: &mut String, colors: &[Color], fonts: &[String]) {
+    if text.is_empty() {
+        return;
+    }
+    let color = colors.last().cloned();
+    let font = fonts.last().cloned();
+    // Tags that change nothing, like `[color=red][/color]`, don't split the text.
+    if let Some(&mut Piece::Text {
+        text: ref mut previous,
+        color: previous_color,
+        font: ref previous_font,
+    }) = pieces.last_mut()
+    {
+        if previous_color == color && *previous_font == font {
+            previous.push_str(text);
+            text.clear();
+            return;
+        }
+    }
+    pieces.push(Piece::Text {
+        text: std::mem::take(text),
+        color,
+        font,
+    });
+}
+
+#[cfg(test)]
+fn text(text: &str, color: Option<Color>, font: Option<&str>) -> Piece {
+    Piece::Text {
+        text: text.to_string(),
+        color,
+        font: font.map(|f| f.to_string()),
+    }
+}
+
+#[test]
+fn parse_colors_and_fonts() {
+    assert_eq!(
+        parse("Score: [color=#ff0]100[/color]!"),
+        Ok(vec![
+            text("Score: ", None, None),
+            text("100", Some(Color::YELLOW), None),
+            text("!", None, None),
+        ])
+    );
+    assert_eq!(
+        parse("[font=big]a[color=red]b[color=blue]c[/color]d[/font]"),
+        Ok(vec![
+            text("a", None, Some("big")),
+            text("b", Some(Color::RED), Some("big")),
+            text("c", Some(Color::BLUE), Some("big")),
+            text("d", Some(Color::RED), Some("big")),
+        ])
+    );
+}
+
+#[test]
+fn parse_images_and_newlines() {
+    assert_eq!(
+        parse("[img=coin] x 3\n[[ok]"),
+        Ok(vec![
+            Piece::Image("coin".to_string()),
+            text(" x 3", None, None),
+            Piece::Newline,
+            text("[ok]", None, None),
+        ])
+    );
+    assert_eq!(
+        parse("a\r\nb"),
+        Ok(vec![
+            text("a", None, None),
+            Piece::Newline,
+            text("b", None, None)
+        ])
+    );
+    assert_eq!(parse(""), Ok(vec![]));
+    // empty tags don't split text
+    assert_eq!(
+        parse("a[color=red][/color]b"),
+        Ok(vec![text("ab", None, None)])
+    );
+}
+
+#[test]
+fn parse_rejects_bad_markup() {
+    assert!(parse("[color=#ff0").is_err());
+    assert!(parse("[color=notacolor]a[/color]").is_err());
+    assert!(parse("a[/color]").is_err());
+    assert!(parse("[bold]a[/bold]").is_err());
+    assert!(parse("[img]").is_err());
+}
@@ -8,3 +8,97 @@ pub use sdl2::rect::Rect;
 ///
 /// Polygon is mostly being set aside for now. May revisit in the future.
 pub type Polygon = Vec<Point>;
+
+/// CornerRadii describes how far each corner of a rounded rectangle is rounded off, in pixels.
+///
+/// A plain `u32` converts into CornerRadii with the same radius on every corner.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct CornerRadii {
+    pub top_left: u32,
+    pub top_right: u32,
+    pub bottom_right: u32,
+    pub bottom_left: u32,
+}
+
+impl CornerRadii {
+    /// Specify each corner individually, clockwise from the top-left.
+    pub fn new(top_left: u32, top_right: u32, bottom_right: u32, bottom_left: u32) -> Self {
+        CornerRadii {
+            top_left,
+            top_right,
+            bottom_right,
+            bottom_left,
+        }
+    }
+
+    /// Round every corner by the same amount.
+    pub fn all(radius: u32) -> Self {
+        CornerRadii::new(radius, radius, radius, radius)
+    }
+}
+
+impl From<u32> for CornerRadii {
+    fn from(radius: u32) -> Self {
+        CornerRadii::all(radius)
+    }
+}
+
+/// Return the outline of a rounded rectangle as a clockwise list of points, starting where the
+/// left edge meets the top-left corner. If the radii don't fit inside the rectangle, they are all scaled down
+/// evenly until they do.
+pub fn rounded_rect_outline(x: f32, y: f32, w: f32, h: f32, radii: CornerRadii) -> Vec<(f32, f32)> {
+    let tl = radii.top_left as f32;
+    let tr = radii.top_right as f32;
+    let br = radii.bottom_right as f32;
+    let bl = radii.bottom_left as f32;
+
+    // Adjacent corners may not overlap, so shrink every corner by the same factor if needed.
+    let mut scale: f32 = 1.0;
+    for &(side, a, b) in &[(w, tl, tr), (w, bl, br), (h, tl, bl), (h, tr, br)] {
+        if a + b > side {
+            scale = scale.min(side / (a + b));
+        }
+    }
+
+    // corner center, radius, and starting angle (in quarter turns, clockwise from +x)
+    let corners = [
+        (x + tl * scale, y + tl * scale, tl * scale, 2.0),
+        (x + w - tr * scale, y + tr * scale, tr * scale, 3.0),
+        (x + w - br * scale, y + h - br * scale, br * scale, 0.0),
+        (x + bl * scale, y + h - bl * scale, bl * scale, 1.0),
+    ];
+
+    let mut points = vec![];
+    for &(cx, cy, r, quarter) in &corners {
+        if r < 0.5 {
+            points.push((cx, cy));
+            continue;
+        }
+        // aim for roughly three pixels per segment
+        let segments = ((r * std::f32::consts::FRAC_PI_2 / 3.0).ceil() as usize).clamp(2, 64);
+        for i in 0..=segments {
+            let angle = (quarter + i as f32 / segments as f32) * std::f32::consts::FRAC_PI_2;
+            points.push((cx + r * angle.cos(), cy + r * angle.sin()));
+        }
+    }
+    points
+}
+
+#[test]
+fn rounded_rect_outline_square_corners() {
+    let points = rounded_rect_outline(10.0, 20.0, 30.0, 40.0, CornerRadii::all(0));
+    assert_eq!(
+        points,
+        vec![(10.0, 20.0), (40.0, 20.0), (40.0, 60.0), (10.0, 60.0)]
+    );
+}
+
+#[test]
+fn rounded_rect_outline_stays_inside() {
+    // radii far too large for the rectangle get scaled down
+    let points = rounded_rect_outline(0.0, 0.0, 20.0, 10.0, CornerRadii::new(50, 0, 50, 0));
+    for &(px, py) in &points {
+        assert!(px >= -0.001 && px <= 20.001, "x out of bounds: {}", px);
+        assert!(py >= -0.001 && py <= 10.001, "y out of bounds: {}", py);
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+
+use serde_json::{self, Map, Value};
+
+use shape;
+use window::Image;
+
+/**
+ * Sprite is one region of a SpriteSheet.
+ *
+ * Texture packing tools often trim the transparent border off each sprite to save space. `offset`
+ * and `size` describe the untrimmed sprite, so that trimmed sprites are drawn in the same place
+ * they would have been without trimming.
+ */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sprite {
+    /// The region of the sheet's image that holds this sprite.
+    pub rect: shape::Rect,
+
+    /// Where `rect` sits inside the untrimmed sprite.
+    pub offset: (i32, i32),
+
+    /// The size of the untrimmed sprite.
+    pub size: (u32, u32),
+
+    /// How long this sprite should be shown for, in milliseconds, if it is a frame of an animation
+    /// and the file it was loaded from specified a duration.
+    pub duration: Option<u32>,
+}
+
+impl Sprite {
+    /// Build an untrimmed Sprite covering `rect`.
+    pub fn new(rect: shape::Rect) -> Self {
+        Sprite {
+            rect,
+            offset: (0, 0),
+            size: (rect.width(), rect.height()),
+            duration: None,
+        }
+    }
+
+    /// Return where `rect` sits inside the untrimmed sprite once the sprite is flipped. Flipping
+    /// mirrors the trimmed border too, so that the sprite doesn't jump around when it turns.
+    pub fn flipped_offset(&self, horizontal: bool, vertical: bool) -> (i32, i32) {
+        let (mut x, mut y) = self.offset;
+        if horizontal {
+            x = self.size.0 as i32 - x - self.rect.width() as i32;
+        }
+        if vertical {
+            y = self.size.1 as i32 - y - self.rect.height() as i32;
+        }
+        (x, y)
+    }
+}
+
+/**
+ * SpriteSheet is an Image that is divided up into many smaller Sprites.
+ *
+ * Packing many sprites into one image is faster to load and faster to draw than keeping each
+ * sprite in its own Image. A SpriteSheet can be sliced up as a regular grid, or described by the
+ * JSON files that TexturePacker and Aseprite export.
+ *
+ * Sprites are looked up either by their index or by their name. See `SpriteKey`.
+ */
+pub struct SpriteSheet {
+    image: Image,
+    sprites: Vec<Sprite>,
+    names: HashMap<String, usize>,
+}
+
+impl SpriteSheet {
+    /// Build a SpriteSheet from a list of Sprites. Sprites can be looked up by the index they have
+    /// in the list.
+    pub fn new(image: Image, sprites: Vec<Sprite>) -> Self {
+        SpriteSheet {
+            image,
+            sprites,
+            names: HashMap::new(),
+        }
+    }
+
+    /// Slice the image into a grid of `cell_width` by `cell_height` cells. Sprites are numbered
+    /// left-to-right, then top-to-bottom, starting at 0. Partial cells at the right and bottom
+    /// edges are left out.
+    pub fn from_grid(image: Image, cell_width: u32, cell_height: u32) -> Self {
+        SpriteSheet::from_grid_spaced(image, cell_width, cell_height, 0, 0)
+    }
+
+    /// Like `from_grid`, for images with `margin` pixels around the edge of the image and
+    /// `spacing` pixels between neighbouring cells.
+    pub fn from_grid_spaced(
+        image: Image,
+        cell_width: u32,
+        cell_height: u32,
+        margin: u32,
+        spacing: u32,
+    ) -> Self {
+        let mut sprites = vec![];
+        if cell_width > 0 && cell_height > 0 {
+            let mut y = margin;
+            while y + cell_height <= image.get_height() {
+                let mut x = margin;
+                while x + cell_width <= image.get_width() {
+                    sprites.push(Sprite::new(shape::Rect::new(
+                        x as i32,
+                        y as i32,
+                        cell_width,
+                        cell_height,
+                    )));
+                    x += cell_width + spacing;
+                }
+                y += cell_height + spacing;
+            }
+        }
+        SpriteSheet::new(image, sprites)
+    }
+
+    /// Load the sprite regions from a JSON description in the format exported by TexturePacker and
+    /// Aseprite. Both the "Hash" and "Array" flavours are supported.
+    ///
+    /// Sprites are indexed in the order they appear in an "Array" file. "Hash" files don't have an
+    /// order, so their sprites are indexed alphabetically by name.
+    pub fn from_json(image: Image, json: &str) -> Result<Self, String> {
+        let (sprites, names) = parse_json_regions(json)?;
+        Ok(SpriteSheet {
+            image,
+            sprites,
+            names,
+        })
+    }
+
+    /// Give a name to the sprite at `index`, so that it can be looked up with a `&str`.
+    pub fn set_name(&mut self, name: &str, index: usize) {
+        self.names.insert(name.to_string(), index);
+    }
+
+    /// Return the Sprite that `key` refers to, or None if there is no such Sprite.
+    pub fn get<K: SpriteKey>(&self, key: K) -> Option<&Sprite> {
+        key.index_in(self).and_then(|i| self.sprites.get(i))
+    }
+
+    /// Return the index of the sprite called `name`.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.get(name).cloned()
+    }
+
+    /// Return the number of Sprites in the sheet.
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
+
+    /// Return true if the sheet has no Sprites.
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    /// Return the Image that the Sprites are cut out of.
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Return the Image that the Sprites are cut out of.
+    pub fn image_mut(&mut self) -> &mut Image {
+        &mut self.image
+    }
+}
+
+/**
+ * SpriteKey is anything that can pick a Sprite out of a SpriteSheet: either its index (`usize`) or
+ * its name (`&str` or `String`).
+ */
+pub trait SpriteKey {
+    /// Return the index of the Sprite that this key refers to.
+    fn index_in(&self, sheet: &SpriteSheet) -> Option<usize>;
+}
+
+impl SpriteKey for usize {
+    fn index_in(&self, sheet: &SpriteSheet) -> Option<usize> {
+        if *self < sheet.len() {
+            Some(*self)
+        } else {
+            None
+        }
+    }
+}
+
+impl SpriteKey for &str {
+    fn index_in(&self, sheet: &SpriteSheet) -> Option<usize> {
+        sheet.index_of(self)
+    }
+}
+
+impl SpriteKey for String {
+    fn index_in(&self, sheet: &SpriteSheet) -> Option<usize> {
+        sheet.index_of(self)
+    }
+}
+
+/// Parse the frames out of a TexturePacker or Aseprite JSON file.
+fn parse_json_regions(json: &str) -> Result<(Vec<Sprite>, HashMap<String, usize>), String> {
+    let json: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let frames = json
+        .get("frames")
+        .ok_or_else(|| "sprite sheet JSON has no \"frames\"".to_string())?;
+
+    let mut named_frames: Vec<(String, &Map<String, Value>)> = vec![];
+    match *frames {
+        // JSON (Array): the name is stored inside each frame
+        Value::Array(ref array) => {
+            for frame in array {
+                let frame = frame
+                    .as_object()
+                    .ok_or_else(|| "sprite sheet frame is not an object".to_string())?;
+                let name = match frame.get("filename") {
+                    Some(Value::String(s)) => s.clone(),
+                    _ => return Err("sprite sheet frame has no \"filename\"".to_string()),
+                };
+                named_frames.push((name, frame));
+            }
+        }
+
+        // JSON (Hash): frames are keyed by name
+        Value::Object(ref object) => {
+            for (name, frame) in object {
+                let frame = frame
+                    .as_object()
+                    .ok_or_else(|| format!("sprite sheet frame {:?} is not an object", name))?;
+                named_frames.push((name.clone(), frame));
+            }
+        }
+
+        _ => return Err("sprite sheet \"frames\" is not an array or object".to_string()),
+    }
+
+    let mut sprites = vec![];
+    let mut names = HashMap::new();
+    for (name, frame) in named_frames {
+        if let Some(&Value::Bool(true)) = frame.get("rotated") {
+            return Err(format!(
+                "sprite {:?} is rotated, which is not supported",
+                name
+            ));
+        }
+
+        let (x, y, w, h) = json_rect(frame.get("frame"))
+            .ok_or_else(|| format!("sprite {:?} has no valid \"frame\"", name))?;
+        let rect = shape::Rect::new(x as i32, y as i32, w as u32, h as u32);
+
+        let mut sprite = Sprite::new(rect);
+        if let Some((x, y, _, _)) = json_rect(frame.get("spriteSourceSize")) {
+            sprite.offset = (x as i32, y as i32);
+        }
+        if let Some(size) = frame.get("sourceSize") {
+            if let (Some(w), Some(h)) = (json_u64(size.get("w")), json_u64(size.get("h"))) {
+                sprite.size = (w as u32, h as u32);
+            }
+        }
+        sprite.duration = json_u64(frame.get("duration")).map(|d| d as u32);
+
+        names.insert(name, sprites.len());
+        sprites.push(sprite);
+    }
+    Ok((sprites, names))
+}
+
+/// Read an `{"x": .., "y": .., "w": .., "h": ..}` object.
+fn json_rect(json: Option<&Value>) -> Option<(u64, u64, u64, u64)> {
+    let json = json?;
+    Some((
+        json_u64(json.get("x"))?,
+        json_u64(json.get("y"))?,
+        json_u64(json.get("w"))?,
+        json_u64(json.get("h"))?,
+    ))
+}
+
+fn json_u64(json: Option<&Value>) -> Option<u64> {
+    json.and_then(|j| j.as_u64())
+}
+
+#[test]
+fn parse_texturepacker_hash() {
+    let (sprites, names) = parse_json_regions(
+        r#"{"frames": {
+            "b.png": {"frame": {"x": 10, "y": 0, "w": 4, "h": 6}, "rotated": false,
+                      "trimmed": true, "spriteSourceSize": {"x": 1, "y": 2, "w": 4, "h": 6},
+                      "sourceSize": {"w": 8, "h": 8}},
+            "a.png": {"frame": {"x": 0, "y": 0, "w": 8, "h": 8}}
+        }}"#,
+    )
+    .unwrap();
+
+    assert_eq!(sprites.len(), 2);
+    assert_eq!(names["a.png"], 0);
+    assert_eq!(names["b.png"], 1);
+    assert_eq!(sprites[0], Sprite::new(shape::Rect::new(0, 0, 8, 8)));
+    assert_eq!(sprites[1].rect, shape::Rect::new(10, 0, 4, 6));
+    assert_eq!(sprites[1].offset, (1, 2));
+    assert_eq!(sprites[1].size, (8, 8));
+}
+
+#[test]
+fn parse_aseprite_array() {
+    let (sprites, names) = parse_json_regions(
+        r#"{"frames": [
+            {"filename": "walk 1", "frame": {"x": 0, "y": 0, "w": 16, "h": 16}, "duration": 100},
+            {"filename": "walk 0", "frame": {"x": 16, "y": 0, "w": 16, "h": 16}, "duration": 250}
+        ], "meta": {}}"#,
+    )
+    .unwrap();
+
+    // array files keep their own order
+    assert_eq!(names["walk 1"], 0);
+    assert_eq!(names["walk 0"], 1);
+    assert_eq!(sprites[0].duration, Some(100));
+    assert_eq!(sprites[1].rect, shape::Rect::new(16, 0, 16, 16));
+}
+
+#[test]
+fn flip_trimmed_sprite() {
+    let mut sprite = Sprite::new(shape::Rect::new(10, 0, 4, 6));
+    sprite.offset = (1, 2);
+    sprite.size = (8, 8);
+    assert_eq!(sprite.flipped_offset(false, false), (1, 2));
+    assert_eq!(sprite.flipped_offset(true, false), (3, 2));
+    assert_eq!(sprite.flipped_offset(false, true), (1, 0));
+    assert_eq!(sprite.flipped_offset(true, true), (3, 0));
+
+    let untrimmed = Sprite::new(shape::Rect::new(0, 0, 8, 8));
+    assert_eq!(untrimmed.flipped_offset(true, true), (0, 0));
+}
+
+#[test]
+fn parse_invalid_json() {
+    assert!(parse_json_regions("").is_err());
+    assert!(parse_json_regions("{}").is_err());
+    assert!(parse_json_regions(r#"{"frames": [{"filename": "a"}]}"#).is_err());
+    assert!(parse_json_regions(
+        r#"{"frames": {"a": {"frame": {"x": 0, "y": 0, "w": 1, "h": 1}, "rotated": true}}}"#
+    )
+    .is_err());
+}
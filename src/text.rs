@@ -0,0 +1,905 @@
+/*!
+ * Laying out text: breaking it into lines that fit inside a width, and lining those lines up.
+ *
+ * Everything here works on character advances, so that it doesn't depend on how a Font stores its
+ * characters.
+ *
+ * Text is handled a grapheme cluster at a time where it matters: accents and other combining
+ * marks stay with the character before them. This follows the simplest Unicode rules rather than
+ * all of them. Right-to-left text is reordered for display with a basic version of the Unicode
+ * Bidirectional Algorithm, and Arabic letters are joined using their presentation forms.
+ */
+
+use std::char;
+
+use color::Color;
+use shape;
+
+/// How lines of text are lined up horizontally. See `TextOptions`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+    /// Stretch the spaces in each line so that it fills the whole width. The last line of each
+    /// paragraph is aligned left.
+    Justify,
+}
+
+/// How a block of text is lined up vertically. See `TextOptions`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VerticalAlign {
+    Top,
+    Middle,
+    Bottom,
+}
+
+/**
+ * TextOptions controls how `Window::print_rect_ex` lays text out inside of a rectangle.
+ *
+ * Start from `TextOptions::default()`, which aligns text to the top-left corner, and override the
+ * fields you need:
+ *
+ * ```rust,no_run
+ * # let mut app = simple::Window::new("hello world", 640, 480);
+ * let options = simple::TextOptions {
+ *     align: simple::Align::Center,
+ *     vertical_align: simple::VerticalAlign::Middle,
+ *     ..Default::default()
+ * };
+ * app.print_rect_ex("Game Over", simple::Rect::new(0, 0, 640, 480), &options);
+ * ```
+ */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextOptions {
+    pub align: Align,
+    pub vertical_align: VerticalAlign,
+
+    /// Extra pixels between one line and the next. May be negative to squeeze lines together.
+    pub line_spacing: i32,
+
+    /// When there are more lines than fit in the rectangle, end the last line that fits with
+    /// "...". Otherwise the lines that don't fit are just left out.
+    pub ellipsis: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        TextOptions {
+            align: Align::Left,
+            vertical_align: VerticalAlign::Top,
+            line_spacing: 0,
+            ellipsis: true,
+        }
+    }
+}
+
+/**
+ * PrintOptions controls how `Window::print_ex` draws text: bigger than the font, and with a drop
+ * shadow or an outline to keep it readable over busy backgrounds.
+ *
+ * ```rust,no_run
+ * # let mut app = simple::Window::new("hello world", 640, 480);
+ * let options = simple::PrintOptions {
+ *     scale: 3.0,
+ *     outline: Some(simple::Color::BLACK),
+ *     ..Default::default()
+ * };
+ * app.print_ex("Level 1", 10, 10, &options);
+ * ```
+ */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PrintOptions {
+    /// How many times bigger than the font to draw the text. Whole numbers keep pixel art fonts
+    /// crisp.
+    pub scale: f32,
+
+    /// Draw a copy of the text in this color behind it, moved by `shadow_offset`.
+    pub shadow: Option<Color>,
+    /// How far the shadow is moved, in pixels of the font, so that it grows with `scale`.
+    pub shadow_offset: (i32, i32),
+
+    /// Draw copies of the text in this color behind it, moved in every direction by up to
+    /// `outline_width`.
+    pub outline: Option<Color>,
+    /// How thick the outline is, in pixels of the font, so that it grows with `scale`. The text
+    /// is drawn once for every offset in the square around it, which is 8 extra times for the
+    /// default of 1, but 80 for 4, so keep thick outlines to short texts.
+    pub outline_width: u32,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        PrintOptions {
+            scale: 1.0,
+            shadow: None,
+            shadow_offset: (1, 1),
+            outline: None,
+            outline_width: 1,
+        }
+    }
+}
+
+impl PrintOptions {
+    /// Return the offsets, in pixels of the font, of the copies of the text that make up the
+    /// outline: every offset up to `outline_width` away in either direction, but (0, 0).
+    pub(crate) fn outline_offsets(&self) -> Vec<(i32, i32)> {
+        if self.outline.is_none() {
+            return vec![];
+        }
+        let thickness = self.outline_width as i32;
+        let mut offsets = vec![];
+        for dy in -thickness..=thickness {
+            for dx in -thickness..=thickness {
+                if (dx, dy) != (0, 0) {
+                    offsets.push((dx, dy));
+                }
+            }
+        }
+        offsets
+    }
+
+    /// Return the area of the screen that text `width` by `height` pixels of the font covers
+    /// when it is printed at (x, y) with these options, shadow and outline included.
+    pub(crate) fn area(&self, x: i32, y: i32, width: u32, height: u32) -> shape::Rect {
+        // left, top, right and bottom, in pixels of the font
+        let (width, height) = (width as i32, height as i32);
+        let mut area = (0, 0, width, height);
+        if self.shadow.is_some() {
+            let (dx, dy) = self.shadow_offset;
+            area = (
+                area.0.min(dx),
+                area.1.min(dy),
+                area.2.max(dx + width),
+                area.3.max(dy + height),
+            );
+        }
+        if self.outline.is_some() {
+            let thickness = self.outline_width as i32;
+            area = (
+                area.0.min(-thickness),
+                area.1.min(-thickness),
+                area.2.max(width + thickness),
+                area.3.max(height + thickness),
+            );
+        }
+        let (left, top, right, bottom) = area;
+        shape::Rect::new(
+            x + (left as f32 * self.scale).floor() as i32,
+            y + (top as f32 * self.scale).floor() as i32,
+            ((right - left) as f32 * self.scale).ceil() as u32,
+            ((bottom - top) as f32 * self.scale).ceil() as u32,
+        )
+    }
+}
+
+/// How wide characters are. Font implements this.
+pub trait Metrics {
+    /// Return how far the pen moves over `ch`.
+    fn char_width(&self, ch: char) -> i32;
+
+    /// Return the extra room between `left` and `right` when they are printed next to each other.
+    /// This is where letter spacing and kerning come in.
+    fn spacing(&self, left: char, right: char) -> i32;
+
+    /// Return how wide `line` is once it is shaped for display, or None if it is displayed the way
+    /// it is written, so that adding up its characters gives its width.
+    fn shaped_width(&self, _line: &str) -> Option<i32> {
+        None
+    }
+}
+
+/// Return how wide the line is when it is displayed, shaped or not.
+pub fn line_width<M: Metrics>(line: &str, metrics: &M) -> i32 {
+    metrics
+        .shaped_width(line)
+        .unwrap_or_else(|| width(line, metrics))
+}
+
+/// Return how wide the text is on one line, adding up its characters.
+pub fn width<M: Metrics>(text: &str, metrics: &M) -> i32 {
+    let mut width = 0;
+    let mut previous = None;
+    for ch in text.chars() {
+        width += step(previous, ch, metrics);
+        previous = Some(ch);
+    }
+    width
+}
+
+/// Return how far the pen moves over `ch` when it comes after `previous`.
+fn step<M: Metrics>(previous: Option<char>, ch: char, metrics: &M) -> i32 {
+    previous.map_or(0, |p| metrics.spacing(p, ch)) + metrics.char_width(ch)
+}
+
+/// One line of laid out text: the bytes `start..end` of the text, and how wide they are.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    pub start: usize,
+    pub end: usize,
+    pub width: i32,
+    /// True if the line ends at a `\n` or at the end of the text, rather than being wrapped.
+    pub ends_paragraph: bool,
+}
+
+/// Break `text` into lines. Lines end at every `\n`, and if `max_width` is given, wherever the
+/// next word wouldn't fit. Words that are too wide on their own are broken between characters.
+/// Spaces where a line is wrapped are left out of both lines.
+pub fn wrap<M: Metrics>(text: &str, max_width: Option<i32>, metrics: &M) -> Vec<Line> {
+    let mut lines = vec![];
+    let mut start = 0;
+    for paragraph in text.split('\n') {
+        let end = start + paragraph.trim_end_matches('\r').len();
+        wrap_paragraph(text, start, end, max_width, metrics, &mut lines);
+        start += paragraph.len() + 1;
+    }
+    lines
+}
+
+/// Return the lines of the text, split at every `\n`, without the `\r` of Windows line endings.
+pub fn split_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.split('\n').map(|line| line.trim_end_matches('\r'))
+}
+
+/// Add the lines of the paragraph at `text[start..end]` to `lines`.
+fn wrap_paragraph<M: Metrics>(
+    text: &str,
+    mut start: usize,
+    end: usize,
+    max_width: Option<i32>,
+    metrics: &M,
+    lines: &mut Vec<Line>,
+) {
+    let mut width = 0;
+    let mut previous = None;
+    // the last run of spaces, as (first byte, byte after it, width before it)
+    let mut spaces: Option<(usize, usize, i32)> = None;
+    // Shaping can change the widths of letters and join some into one, so lines that are shaped
+    // have to be measured whole instead of adding up their characters.
+    let shaped = metrics.shaped_width(&text[start..end]).is_some();
+    let measure = |start: usize, end: usize, width: i32| {
+        if shaped {
+            line_width(&text[start..end], metrics)
+        } else {
+            width
+        }
+    };
+
+    let paragraph_start = start;
+    for (i, ch) in text[start..end].char_indices() {
+        let i = paragraph_start + i;
+        let mut char_width = step(previous, ch, metrics);
+        let continues = previous.is_some_and(|p| continues_grapheme(p, ch));
+        previous = Some(ch);
+        if ch == ' ' {
+            // Spaces may hang over the edge, since they are dropped if the line is wrapped there.
+            spaces = match spaces {
+                Some((first, after, before)) if after == i => Some((first, i + 1, before)),
+                _ => Some((i, i + 1, width)),
+            };
+            width += char_width;
+            continue;
+        }
+
+        let too_wide = max_width
+            .is_some_and(|max| measure(start, i + ch.len_utf8(), width + char_width) > max);
+        if too_wide && i > start && !continues {
+            let end = match spaces {
+                Some((first, after, before)) if first > start => {
+                    lines.push(Line {
+                        start,
+                        end: first,
+                        width: measure(start, first, before),
+                        ends_paragraph: false,
+                    });
+                    after
+                }
+                _ => {
+                    lines.push(Line {
+                        start,
+                        end: i,
+                        width: measure(start, i, width),
+                        ends_paragraph: false,
+                    });
+                    i
+                }
+            };
+            start = end;
+            spaces = None;
+            width = self::width(&text[start..i], metrics);
+            char_width = step(text[start..i].chars().last(), ch, metrics);
+        }
+        width += char_width;
+    }
+
+    // Spaces at the end of the paragraph don't count.
+    let (end, width) = match spaces {
+        Some((first, after, before)) if after == end && first > start => (first, before),
+        _ => (end, width),
+    };
+    lines.push(Line {
+        start,
+        end,
+        width: measure(start, end, width),
+        ends_paragraph: true,
+    });
+}
+
+/// Shorten the line until it fits inside `max_width` with "..." after it. Return the shortened
+/// line, whose width includes the "...".
+pub fn ellipsize<M: Metrics>(text: &str, line: Line, max_width: i32, metrics: &M) -> Line {
+    let mut line = line;
+    loop {
+        let shortened = &text[line.start..line.end];
+        line.width = line_width(&format!("{}...", shortened), metrics);
+        if line.start == line.end || (line.width <= max_width && !shortened.ends_with(' ')) {
+            return line;
+        }
+        line.end -= shortened.chars().last().unwrap().len_utf8();
+    }
+}
+
+/// Return the x position of each character of the line, starting from `x` and lined up with
+/// `align` inside of `width`.
+pub fn place_line<M: Metrics>(
+    text: &str,
+    line: Line,
+    x: i32,
+    width: i32,
+    align: Align,
+    metrics: &M,
+) -> Vec<(char, i32)> {
+    let chars = &text[line.start..line.end];
+    let mut pen = match align {
+        Align::Left | Align::Justify => x,
+        Align::Center => x + (width - line.width) / 2,
+        Align::Right => x + width - line.width,
+    };
+
+    // Justified lines share the extra room out between their spaces, a pixel at a time.
+    let spaces = chars.chars().filter(|&ch| ch == ' ').count() as i32;
+    let (mut extra, mut remainder) = (0, 0);
+    if align == Align::Justify && !line.ends_paragraph && spaces > 0 && line.width < width {
+        extra = (width - line.width) / spaces;
+        remainder = (width - line.width) % spaces;
+    }
+
+    let mut placed = Vec::with_capacity(chars.len());
+    let mut previous = None;
+    for ch in chars.chars() {
+        if let Some(p) = previous {
+            pen += metrics.spacing(p, ch);
+        }
+        placed.push((ch, pen));
+        pen += metrics.char_width(ch);
+        if ch == ' ' && (extra > 0 || remainder > 0) {
+            pen += extra;
+            if remainder > 0 {
+                pen += 1;
+                remainder -= 1;
+            }
+        }
+        previous = Some(ch);
+    }
+    placed
+}
+
+/// Return true if `ch` belongs to the same grapheme cluster as `previous`, the character before
+/// it, rather than starting a new one.
+pub fn continues_grapheme(previous: char, ch: char) -> bool {
+    (previous == '\r' && ch == '\n') || previous == ZERO_WIDTH_JOINER || is_combining(ch)
+}
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+/// Ranges of characters that are drawn on top of, or joined to, the character before them:
+/// combining accents, the vowel marks of Hebrew, Arabic, Devanagari and Thai, Japanese sound
+/// marks, variation selectors, zero-width joiners and emoji skin tones.
+const COMBINING: [(u32, u32); 29] = [
+    (0x0300, 0x036F),
+    (0x0483, 0x0489),
+    (0x0591, 0x05BD),
+    (0x05BF, 0x05BF),
+    (0x05C1, 0x05C2),
+    (0x05C4, 0x05C5),
+    (0x05C7, 0x05C7),
+    (0x0610, 0x061A),
+    (0x064B, 0x065F),
+    (0x0670, 0x0670),
+    (0x06D6, 0x06DC),
+    (0x06DF, 0x06E4),
+    (0x06E7, 0x06E8),
+    (0x06EA, 0x06ED),
+    (0x0900, 0x0903),
+    (0x093A, 0x093C),
+    (0x093E, 0x094F),
+    (0x0951, 0x0957),
+    (0x0962, 0x0963),
+    (0x0E31, 0x0E31),
+    (0x0E34, 0x0E3A),
+    (0x0E47, 0x0E4E),
+    (0x1AB0, 0x1AFF),
+    (0x1DC0, 0x1DFF),
+    (0x200C, 0x200D),
+    (0x20D0, 0x20FF),
+    (0x3099, 0x309A),
+    (0xFE00, 0xFE0F),
+    (0xFE20, 0xFE2F),
+];
+
+/// Return true if `ch` never starts a grapheme cluster of its own. See `COMBINING`.
+pub fn is_combining(ch: char) -> bool {
+    let c = ch as u32;
+    COMBINING
+        .iter()
+        .any(|&(first, last)| first <= c && c <= last)
+        || (0x1F3FB..=0x1F3FF).contains(&c)
+        || (0xE0100..=0xE01EF).contains(&c)
+}
+
+/// An iterator over the grapheme clusters of a string and their byte indices. See `graphemes`.
+pub struct Graphemes<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = (usize, &'a str);
+
+    fn next(&mut self) -> Option<(usize, &'a str)> {
+        let rest = &self.text[self.position..];
+        let mut chars = rest.char_indices();
+        let (_, mut previous) = chars.next()?;
+        let mut end = rest.len();
+        for (i, ch) in chars {
+            if !continues_grapheme(previous, ch) {
+                end = i;
+                break;
+            }
+            previous = ch;
+        }
+        let start = self.position;
+        self.position += end;
+        Some((start, &rest[..end]))
+    }
+}
+
+/// Return the grapheme clusters of the text, like `str::char_indices` returns its characters.
+pub fn graphemes(text: &str) -> Graphemes<'_> {
+    Graphemes { text, position: 0 }
+}
+
+const TATWEEL: char = '\u{640}';
+
+/// The Arabic letters that change shape depending on their neighbours, with the first of their
+/// presentation forms, and whether they join to the letter after them as well as the one before.
+/// Letters that join both ways have isolated, final, initial and medial forms, in that order.
+/// The others only have isolated and final forms, since they never join the letter after them.
+const ARABIC_FORMS: [(char, u32, bool); 35] = [
+    ('\u{622}', 0xFE81, false),
+    ('\u{623}', 0xFE83, false),
+    ('\u{624}', 0xFE85, false),
+    ('\u{625}', 0xFE87, false),
+    ('\u{626}', 0xFE89, true),
+    ('\u{627}', 0xFE8D, false),
+    ('\u{628}', 0xFE8F, true),
+    ('\u{629}', 0xFE93, false),
+    ('\u{62A}', 0xFE95, true),
+    ('\u{62B}', 0xFE99, true),
+    ('\u{62C}', 0xFE9D, true),
+    ('\u{62D}', 0xFEA1, true),
+    ('\u{62E}', 0xFEA5, true),
+    ('\u{62F}', 0xFEA9, false),
+    ('\u{630}', 0xFEAB, false),
+    ('\u{631}', 0xFEAD, false),
+    ('\u{632}', 0xFEAF, false),
+    ('\u{633}', 0xFEB1, true),
+    ('\u{634}', 0xFEB5, true),
+    ('\u{635}', 0xFEB9, true),
+    ('\u{636}', 0xFEBD, true),
+    ('\u{637}', 0xFEC1, true),
+    ('\u{638}', 0xFEC5, true),
+    ('\u{639}', 0xFEC9, true),
+    ('\u{63A}', 0xFECD, true),
+    ('\u{641}', 0xFED1, true),
+    ('\u{642}', 0xFED5, true),
+    ('\u{643}', 0xFED9, true),
+    ('\u{644}', 0xFEDD, true),
+    ('\u{645}', 0xFEE1, true),
+    ('\u{646}', 0xFEE5, true),
+    ('\u{647}', 0xFEE9, true),
+    ('\u{648}', 0xFEED, false),
+    ('\u{649}', 0xFEEF, false),
+    ('\u{64A}', 0xFEF1, true),
+];
+
+fn arabic_forms(ch: char) -> Option<(u32, bool)> {
+    ARABIC_FORMS
+        .binary_search_by_key(&ch, |&(letter, _, _)| letter)
+        .ok()
+        .map(|i| (ARABIC_FORMS[i].1, ARABIC_FORMS[i].2))
+}
+
+/// Return true if the letter after `ch` joins onto it.
+fn joins_after(ch: char) -> bool {
+    ch == TATWEEL || arabic_forms(ch).is_some_and(|(_, both)| both)
+}
+
+/// Return the isolated form of the ligature of lam followed by `alef`, if there is one. The
+/// final form comes right after it.
+fn lam_alef(alef: char) -> Option<u32> {
+    match alef {
+        '\u{622}' => Some(0xFEF5),
+        '\u{623}' => Some(0xFEF7),
+        '\u{625}' => Some(0xFEF9),
+        '\u{627}' => Some(0xFEFB),
+        _ => None,
+    }
+}
+
+/// Replace Arabic letters with the presentation forms that join them to their neighbours, where
+/// `has_char` says the font has the form. The text is in logical order, as it was written.
+pub fn shape_arabic<F: Fn(char) -> bool>(text: &str, has_char: F) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut shaped = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        i += 1;
+        let (first, both) = match arabic_forms(ch) {
+            Some(forms) => forms,
+            None => {
+                shaped.push(ch);
+                continue;
+            }
+        };
+        // Marks sit on top of letters, so they don't get in the way of joining.
+        let previous = chars[..i - 1].iter().rev().find(|&&c| !is_combining(c));
+        let next = chars[i..].iter().find(|&&c| !is_combining(c));
+        let joins_before = previous.is_some_and(|&p| joins_after(p));
+        let joins_next = both && next.is_some_and(|&n| n == TATWEEL || arabic_forms(n).is_some());
+
+        let ligature = match chars.get(i) {
+            Some(&alef) if ch == '\u{644}' => lam_alef(alef),
+            _ => None,
+        };
+        // Without the ligature, lam still joins to the alef after it.
+        let joined = first + joins_before as u32 + 2 * joins_next as u32;
+        let mut forms = vec![(joined, 0)];
+        if let Some(ligature) = ligature {
+            forms.insert(0, (ligature + joins_before as u32, 1));
+        }
+        let found = forms
+            .into_iter()
+            .filter_map(|(form, skip)| char::from_u32(form).map(|form| (form, skip)))
+            .find(|&(form, _)| has_char(form));
+        match found {
+            Some((form, skip)) => {
+                shaped.push(form);
+                i += skip;
+            }
+            None => shaped.push(ch),
+        }
+    }
+    shaped
+}
+
+/// Return true for letters of right-to-left scripts: Hebrew, Arabic, Syriac, Thaana and their
+/// presentation forms.
+fn is_right_to_left(ch: char) -> bool {
+    let c = ch as u32;
+    (0x0590..=0x08FF).contains(&c)
+        || (0xFB1D..=0xFDFF).contains(&c)
+        || (0xFE70..=0xFEFF).contains(&c)
+}
+
+/// Return the text in the order its characters appear on the screen from left to right, which
+/// differs from the order they were written in when there is right-to-left text. The direction
+/// of the whole text is that of its first letter. Numbers and left-to-right words inside
+/// right-to-left text keep their own order, and brackets in right-to-left text are mirrored.
+/// Combining marks stay after the characters they belong to.
+pub fn display_order(text: &str) -> String {
+    if !text.chars().any(is_right_to_left) {
+        return text.to_string();
+    }
+    let clusters: Vec<&str> = graphemes(text).map(|(_, cluster)| cluster).collect();
+
+    // The direction of each cluster: Some(true) for right-to-left, Some(false) for left-to-right
+    // and None for spaces and punctuation, which take the direction of the text around them.
+    let strong: Vec<Option<bool>> = clusters
+        .iter()
+        .map(|cluster| {
+            let ch = cluster.chars().next().unwrap();
+            if is_right_to_left(ch) {
+                Some(true)
+            } else if ch.is_alphanumeric() {
+                Some(false)
+            } else {
+                None
+            }
+        })
+        .collect();
+    let right_to_left = strong.iter().find_map(|&s| s).unwrap_or(false);
+
+    // Embedding levels: even for left-to-right, odd for right-to-left.
+    let mut levels = Vec::with_capacity(clusters.len());
+    for (i, &direction) in strong.iter().enumerate() {
+        let direction = direction.unwrap_or_else(|| {
+            let before = strong[..i].iter().rev().find_map(|&s| s);
+            let after = strong[i + 1..].iter().find_map(|&s| s);
+            match (
+                before.unwrap_or(right_to_left),
+                after.unwrap_or(right_to_left),
+            ) {
+                (a, b) if a == b => a,
+                _ => right_to_left,
+            }
+        });
+        levels.push(match (right_to_left, direction) {
+            (false, false) => 0,
+            (false, true) | (true, true) => 1,
+            (true, false) => 2,
+        });
+    }
+
+    // From the highest level down, reverse every run at that level or above.
+    let mut order: Vec<usize> = (0..clusters.len()).collect();
+    for level in (1..=*levels.iter().max().unwrap()).rev() {
+        let mut i = 0;
+        while i < order.len() {
+            if levels[order[i]] < level {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < order.len() && levels[order[i]] >= level {
+                i += 1;
+            }
+            order[start..i].reverse();
+        }
+    }
+
+    let mut displayed = String::with_capacity(text.len());
+    for i in order {
+        if levels[i] % 2 == 1 {
+            displayed.extend(clusters[i].chars().map(mirror));
+        } else {
+            displayed.push_str(clusters[i]);
+        }
+    }
+    displayed
+}
+
+/// Return the mirror image of a bracket, for right-to-left text.
+fn mirror(ch: char) -> char {
+    match ch {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        _ => ch,
+    }
+}
+
+/// 'W' is twice as wide as every other character, and "AV" is kerned together.
+#[cfg(test)]
+struct TestMetrics {
+    letter_spacing: i32,
+}
+
+#[cfg(test)]
+impl Metrics for TestMetrics {
+    fn char_width(&self, ch: char) -> i32 {
+        if ch == 'W' {
+            20
+        } else {
+            10
+        }
+    }
+
+    fn spacing(&self, left: char, right: char) -> i32 {
+        match (left, right) {
+            ('A', 'V') => self.letter_spacing - 4,
+            _ => self.letter_spacing,
+        }
+    }
+}
+
+#[cfg(test)]
+const TEST_METRICS: TestMetrics = TestMetrics { letter_spacing: 0 };
+
+#[cfg(test)]
+fn line_strings(text: &str, lines: &[Line]) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| text[line.start..line.end].to_string())
+        .collect()
+}
+
+#[test]
+fn wrap_words() {
+    let text = "the quick  brown fox\njumps";
+    let lines = wrap(text, Some(100), &TEST_METRICS);
+    assert_eq!(
+        line_strings(text, &lines),
+        vec!["the quick", "brown fox", "jumps"]
+    );
+    assert_eq!(lines[0].width, 90);
+    assert!(!lines[0].ends_paragraph);
+    assert!(lines[1].ends_paragraph);
+
+    // without a width, only newlines break lines
+    let lines = wrap("a b \r\n\nc", None, &TEST_METRICS);
+    assert_eq!(line_strings("a b \r\n\nc", &lines), vec!["a b", "", "c"]);
+    assert_eq!(lines[0].width, 30);
+    // print splits lines the same way
+    let split: Vec<&str> = split_lines("a b \r\n\nc").collect();
+    assert_eq!(split, vec!["a b ", "", "c"]);
+}
+
+#[test]
+fn wrap_long_words() {
+    let text = "WWWWW ab";
+    let lines = wrap(text, Some(45), &TEST_METRICS);
+    assert_eq!(line_strings(text, &lines), vec!["WW", "WW", "W", "ab"]);
+
+    // a character wider than the line still goes somewhere
+    let lines = wrap("W", Some(5), &TEST_METRICS);
+    assert_eq!(line_strings("W", &lines), vec!["W"]);
+
+    // leading spaces are kept, and aren't a place to wrap
+    let lines = wrap("  abcdef", Some(50), &TEST_METRICS);
+    assert_eq!(line_strings("  abcdef", &lines), vec!["  abc", "def"]);
+}
+
+#[test]
+fn print_options_area() {
+    let plain = PrintOptions::default();
+    assert_eq!(plain.area(5, 6, 10, 8), shape::Rect::new(5, 6, 10, 8));
+    assert!(plain.outline_offsets().is_empty());
+
+    // the shadow only grows the area on its own side
+    let shadow = PrintOptions {
+        shadow: Some(Color::BLACK),
+        shadow_offset: (2, -1),
+        ..Default::default()
+    };
+    assert_eq!(shadow.area(0, 0, 10, 8), shape::Rect::new(0, -1, 12, 9));
+
+    // the outline grows it all round, and everything is scaled around (x, y)
+    let outline = PrintOptions {
+        scale: 1.5,
+        outline: Some(Color::BLACK),
+        outline_width: 2,
+        ..Default::default()
+    };
+    assert_eq!(outline.area(10, 10, 10, 8), shape::Rect::new(7, 7, 21, 18));
+    let offsets = outline.outline_offsets();
+    assert_eq!(offsets.len(), 24);
+    assert!(offsets.contains(&(-2, 2)) && offsets.contains(&(1, 0)));
+    assert!(!offsets.contains(&(0, 0)));
+}
+
+#[test]
+fn ellipsize_lines() {
+    let text = "hello there";
+    let line = wrap(text, None, &TEST_METRICS)[0];
+    let short = ellipsize(text, line, 90, &TEST_METRICS);
+    assert_eq!(&text[short.start..short.end], "hello");
+    assert_eq!(short.width, 80);
+
+    let short = ellipsize(text, line, 10, &TEST_METRICS);
+    assert_eq!((short.start, short.end, short.width), (0, 0, 30));
+}
+
+#[test]
+fn place_aligned_lines() {
+    let text = "a b  c";
+    let line = Line {
+        ends_paragraph: false,
+        ..wrap(text, None, &TEST_METRICS)[0]
+    };
+    let xs = |align| -> Vec<i32> {
+        place_line(text, line, 100, 100, align, &TEST_METRICS)
+            .iter()
+            .map(|&(_, x)| x)
+            .collect()
+    };
+    assert_eq!(xs(Align::Left), vec![100, 110, 120, 130, 140, 150]);
+    assert_eq!(xs(Align::Center), vec![120, 130, 140, 150, 160, 170]);
+    assert_eq!(xs(Align::Right), vec![140, 150, 160, 170, 180, 190]);
+    // 40 pixels shared between three spaces
+    assert_eq!(xs(Align::Justify), vec![100, 110, 134, 144, 167, 190]);
+}
+
+#[test]
+fn letter_spacing_and_kerning() {
+    let metrics = TestMetrics { letter_spacing: 2 };
+    // kerning takes 4 off the spacing between A and V
+    assert_eq!(width("AVA", &metrics), 30);
+    assert_eq!(width("", &metrics), 0);
+
+    let text = "AV AV";
+    let lines = wrap(text, Some(40), &metrics);
+    assert_eq!(line_strings(text, &lines), vec!["AV", "AV"]);
+    assert_eq!(lines[1].width, 18);
+    let placed = place_line(text, lines[1], 0, 40, Align::Left, &metrics);
+    assert_eq!(placed, vec![('A', 0), ('V', 8)]);
+}
+
+#[test]
+fn grapheme_clusters() {
+    let clusters: Vec<(usize, &str)> = graphemes("e\u{301}a\r\nb").collect();
+    assert_eq!(
+        clusters,
+        vec![(0, "e\u{301}"), (3, "a"), (4, "\r\n"), (6, "b")]
+    );
+    // emoji joined with zero-width joiners, and with a skin tone
+    let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+    assert_eq!(graphemes(family).count(), 1);
+    assert_eq!(graphemes("\u{1F44B}\u{1F3FD}!").count(), 2);
+    assert_eq!(graphemes("").count(), 0);
+
+    // lines aren't broken inside a cluster
+    let text = "ae\u{301}\u{301}";
+    let lines = wrap(text, Some(15), &TEST_METRICS);
+    assert_eq!(line_strings(text, &lines), vec!["a", "e\u{301}\u{301}"]);
+}
+
+#[test]
+fn arabic_shaping() {
+    let all = |_| true;
+    // beh beh: initial, final
+    assert_eq!(shape_arabic("\u{628}\u{628}", all), "\u{FE91}\u{FE90}");
+    // beh beh beh, with a mark on the middle one: initial, medial, final
+    assert_eq!(
+        shape_arabic("\u{628}\u{628}\u{64E}\u{628}", all),
+        "\u{FE91}\u{FE92}\u{64E}\u{FE90}"
+    );
+    // alef doesn't join the letter after it
+    assert_eq!(shape_arabic("\u{627}\u{628} x", all), "\u{FE8D}\u{FE8F} x");
+    // lam alef ligature, isolated and final
+    assert_eq!(shape_arabic("\u{644}\u{627}", all), "\u{FEFB}");
+    assert_eq!(
+        shape_arabic("\u{628}\u{644}\u{627}", all),
+        "\u{FE91}\u{FEFC}"
+    );
+    // without the ligature, lam and alef still join
+    let no_ligature = |ch| ch != '\u{FEFB}' && ch != '\u{FEFC}';
+    assert_eq!(
+        shape_arabic("\u{644}\u{627}", no_ligature),
+        "\u{FEDF}\u{FE8E}"
+    );
+    assert_eq!(
+        shape_arabic("\u{628}\u{644}\u{627}", no_ligature),
+        "\u{FE91}\u{FEE0}\u{FE8E}"
+    );
+    // forms the font doesn't have are left alone
+    assert_eq!(
+        shape_arabic("\u{628}\u{628}", |ch| ch != '\u{FE91}'),
+        "\u{628}\u{FE90}"
+    );
+}
+
+#[test]
+fn bidirectional_text() {
+    assert_eq!(display_order("abc"), "abc");
+    // right-to-left words inside left-to-right text
+    assert_eq!(
+        display_order("ab \u{5D0}\u{5D1}\u{5D2} cd"),
+        "ab \u{5D2}\u{5D1}\u{5D0} cd"
+    );
+    // numbers keep their order inside right-to-left text
+    assert_eq!(display_order("\u{5D0}\u{5D1} 12"), "12 \u{5D1}\u{5D0}");
+    // brackets are mirrored, and marks stay after their letters
+    assert_eq!(
+        display_order("\u{5D0}(\u{5D1}\u{5B8})"),
+        "(\u{5D1}\u{5B8})\u{5D0}"
+    );
+}
@@ -0,0 +1,204 @@
+use shape::Rect;
+
+/**
+ * Transform is a 2D affine transformation: any combination of translation, scaling and rotation.
+ *
+ * The Window keeps a current Transform that every draw call passes through. Most of the time you
+ * will change it with `Window::translate`, `Window::scale` and `Window::rotate` instead of building
+ * a Transform yourself.
+ *
+ * Internally this is the matrix
+ *
+ * ```text
+ * | a  c  tx |
+ * | b  d  ty |
+ * ```
+ */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    tx: f32,
+    ty: f32,
+}
+
+impl Transform {
+    /// The Transform that leaves every point where it is.
+    pub fn identity() -> Self {
+        Transform::translation(0.0, 0.0)
+    }
+
+    /// Move every point by (x, y).
+    pub fn translation(x: f32, y: f32) -> Self {
+        Transform {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            tx: x,
+            ty: y,
+        }
+    }
+
+    /// Stretch every point away from the origin, by `x` horizontally and `y` vertically.
+    pub fn scaling(x: f32, y: f32) -> Self {
+        Transform {
+            a: x,
+            d: y,
+            ..Transform::identity()
+        }
+    }
+
+    /// Turn every point around the origin by `angle` radians. Because y points down on the screen,
+    /// positive angles turn clockwise.
+    pub fn rotation(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Transform {
+            a: cos,
+            b: sin,
+            c: -sin,
+            d: cos,
+            ..Transform::identity()
+        }
+    }
+
+    /// Return the Transform that applies `self` first and then `next`.
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            a: next.a * self.a + next.c * self.b,
+            b: next.b * self.a + next.d * self.b,
+            c: next.a * self.c + next.c * self.d,
+            d: next.b * self.c + next.d * self.d,
+            tx: next.a * self.tx + next.c * self.ty + next.tx,
+            ty: next.b * self.tx + next.d * self.ty + next.ty,
+        }
+    }
+
+    /// Return the Transform that undoes this one, or None if this Transform squashes everything
+    /// onto a line (for example, a scale of 0).
+    pub fn inverse(&self) -> Option<Transform> {
+        let det = self.a * self.d - self.b * self.c;
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        Some(Transform {
+            a: self.d / det,
+            b: -self.b / det,
+            c: -self.c / det,
+            d: self.a / det,
+            tx: (self.c * self.ty - self.d * self.tx) / det,
+            ty: (self.b * self.tx - self.a * self.ty) / det,
+        })
+    }
+
+    /// Return where the point (x, y) ends up.
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.a * x + self.c * y + self.tx,
+            self.b * x + self.d * y + self.ty,
+        )
+    }
+
+    /// Return the smallest Rect that holds all of `rect` once it has been transformed.
+    pub fn apply_rect(&self, rect: Rect) -> Rect {
+        let (left, top) = (rect.x() as f32, rect.y() as f32);
+        let (right, bottom) = (rect.right() as f32, rect.bottom() as f32);
+        let corners = [
+            self.apply(left, top),
+            self.apply(right, top),
+            self.apply(right, bottom),
+            self.apply(left, bottom),
+        ];
+        let (mut min_x, mut min_y) = corners[0];
+        let (mut max_x, mut max_y) = corners[0];
+        for &(x, y) in &corners[1..] {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        // Round first, so that rectangles that are only moved and scaled don't grow a pixel.
+        let (min_x, min_y) = (min_x.round(), min_y.round());
+        let (max_x, max_y) = (max_x.round(), max_y.round());
+        Rect::new(
+            min_x as i32,
+            min_y as i32,
+            (max_x - min_x) as u32,
+            (max_y - min_y) as u32,
+        )
+    }
+
+    /// Return true if this Transform leaves every point where it is.
+    pub fn is_identity(&self) -> bool {
+        *self == Transform::identity()
+    }
+
+    /// Return the scale factor if this Transform only translates and scales both axes by the same
+    /// positive amount. Rectangles stay rectangles under such a Transform.
+    pub(crate) fn uniform_scale(&self) -> Option<f32> {
+        if self.b == 0.0 && self.c == 0.0 && self.a == self.d && self.a > 0.0 {
+            Some(self.a)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+#[cfg(test)]
+fn assert_near(actual: (f32, f32), expected: (f32, f32)) {
+    assert!(
+        (actual.0 - expected.0).abs() < 1e-4 && (actual.1 - expected.1).abs() < 1e-4,
+        "{:?} != {:?}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn transform_compose() {
+    let t = Transform::scaling(2.0, 3.0).then(&Transform::translation(10.0, 20.0));
+    assert_near(t.apply(1.0, 1.0), (12.0, 23.0));
+
+    // a quarter turn clockwise takes the x axis to the y axis
+    let r = Transform::rotation(std::f32::consts::FRAC_PI_2);
+    assert_near(r.apply(1.0, 0.0), (0.0, 1.0));
+
+    let all = r.then(&t);
+    assert_near(all.apply(1.0, 0.0), (10.0, 23.0));
+    assert!(Transform::identity().is_identity());
+    assert!(!all.is_identity());
+}
+
+#[test]
+fn transform_inverse() {
+    let t = Transform::rotation(0.7)
+        .then(&Transform::scaling(2.0, 0.5))
+        .then(&Transform::translation(-4.0, 9.0));
+    let (x, y) = t.apply(3.0, -5.0);
+    assert_near(t.inverse().unwrap().apply(x, y), (3.0, -5.0));
+    assert!(Transform::scaling(0.0, 1.0).inverse().is_none());
+}
+
+#[test]
+fn transform_rect() {
+    let t = Transform::scaling(2.0, 2.0).then(&Transform::translation(5.0, -5.0));
+    assert_eq!(
+        t.apply_rect(Rect::new(1, 1, 10, 4)),
+        Rect::new(7, -3, 20, 8)
+    );
+
+    // a quarter turn puts the rectangle to the left of the origin
+    let r = Transform::rotation(std::f32::consts::FRAC_PI_2);
+    assert_eq!(
+        r.apply_rect(Rect::new(0, 0, 10, 4)),
+        Rect::new(-4, 0, 4, 10)
+    );
+}
@@ -0,0 +1,828 @@
+/*!
+ * Reading and rasterizing TrueType fonts.
+ *
+ * The file format is documented at https://learn.microsoft.com/en-us/typography/opentype/spec/
+ *
+ * Only what is needed to draw horizontal text is read: the character map, the horizontal metrics
+ * and the glyph outlines, including composite glyphs. Hinting instructions are ignored. OpenType
+ * fonts with CFF outlines (usually `.otf` files) are not supported. From a font collection, only
+ * the first font is read.
+ */
+
+use std::convert::TryFrom;
+
+/// A piece of a glyph outline, in font units with y pointing up.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Segment {
+    Line((f32, f32), (f32, f32)),
+    /// A quadratic Bézier curve from the first point to the last, pulled towards the middle one.
+    Quad((f32, f32), (f32, f32), (f32, f32)),
+}
+
+/// A straight line between two points.
+type Line = ((f32, f32), (f32, f32));
+
+/// A glyph turned into pixels.
+pub struct GlyphBitmap {
+    pub width: u32,
+    pub height: u32,
+    /// Where the top-left corner of the bitmap goes, relative to the pen position on the baseline,
+    /// with y pointing down.
+    pub offset: (i32, i32),
+    /// How much of each pixel the glyph covers, from 0 to 255, row by row.
+    pub coverage: Vec<u8>,
+}
+
+/// A parsed TrueType font.
+pub struct TrueType {
+    data: Vec<u8>,
+    units_per_em: u16,
+    index_to_loc_format: i16,
+    num_glyphs: u16,
+    num_h_metrics: u16,
+    ascent: i16,
+    descent: i16,
+    line_gap: i16,
+    cmap: usize,
+    cmap_format: u16,
+    glyf: usize,
+    loca: usize,
+    hmtx: usize,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, String> {
+    match data.get(offset..offset + 2) {
+        Some(b) => Ok((b[0] as u16) << 8 | b[1] as u16),
+        None => Err("truetype font ends unexpectedly".to_string()),
+    }
+}
+
+fn read_i16(data: &[u8], offset: usize) -> Result<i16, String> {
+    read_u16(data, offset).map(|v| v as i16)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, String> {
+    Ok((read_u16(data, offset)? as u32) << 16 | read_u16(data, offset + 2)? as u32)
+}
+
+/// Return the offset of the table called `tag` within the font that starts at `start`.
+fn find_table(data: &[u8], start: usize, tag: &[u8; 4]) -> Result<Option<usize>, String> {
+    let num_tables = read_u16(data, start + 4)? as usize;
+    for i in 0..num_tables {
+        let record = start + 12 + i * 16;
+        if data.get(record..record + 4) == Some(&tag[..]) {
+            return Ok(Some(read_u32(data, record + 8)? as usize));
+        }
+    }
+    Ok(None)
+}
+
+/// Return the offset of the best character map subtable for Unicode, with its format.
+fn find_cmap(data: &[u8], cmap: usize) -> Result<(usize, u16), String> {
+    let num_tables = read_u16(data, cmap + 2)? as usize;
+    let mut best = None;
+    for i in 0..num_tables {
+        let record = cmap + 4 + i * 8;
+        let platform = read_u16(data, record)?;
+        let encoding = read_u16(data, record + 2)?;
+        let offset = cmap + read_u32(data, record + 4)? as usize;
+        let unicode = platform == 0 || (platform == 3 && (encoding == 1 || encoding == 10));
+        if !unicode {
+            continue;
+        }
+        match read_u16(data, offset)? {
+            // format 12 covers every plane, so it always wins
+            12 => return Ok((offset, 12)),
+            4 => best = Some((offset, 4)),
+            _ => (),
+        }
+    }
+    best.ok_or_else(|| "truetype font has no unicode character map".to_string())
+}
+
+impl TrueType {
+    /// Parse a `.ttf` or `.ttc` file.
+    pub fn parse(data: &[u8]) -> Result<TrueType, String> {
+        let mut start = 0;
+        match data.get(0..4) {
+            Some(b"\0\x01\0\0") | Some(b"true") => (),
+            Some(b"ttcf") => start = read_u32(data, 12)? as usize,
+            Some(b"OTTO") => return Err("fonts with CFF outlines are not supported".to_string()),
+            _ => return Err("not a truetype font".to_string()),
+        }
+
+        let table = |tag: &[u8; 4]| -> Result<usize, String> {
+            find_table(data, start, tag)?.ok_or_else(|| {
+                format!(
+                    "truetype font has no {} table",
+                    String::from_utf8_lossy(&tag[..])
+                )
+            })
+        };
+        let head = table(b"head")?;
+        let hhea = table(b"hhea")?;
+        let maxp = table(b"maxp")?;
+        let (cmap, cmap_format) = find_cmap(data, table(b"cmap")?)?;
+
+        let font = TrueType {
+            units_per_em: read_u16(data, head + 18)?,
+            index_to_loc_format: read_i16(data, head + 50)?,
+            num_glyphs: read_u16(data, maxp + 4)?,
+            ascent: read_i16(data, hhea + 4)?,
+            descent: read_i16(data, hhea + 6)?,
+            line_gap: read_i16(data, hhea + 8)?,
+            num_h_metrics: read_u16(data, hhea + 34)?,
+            cmap,
+            cmap_format,
+            glyf: table(b"glyf")?,
+            loca: table(b"loca")?,
+            hmtx: table(b"hmtx")?,
+            data: data.to_vec(),
+        };
+        if font.units_per_em == 0 || font.num_h_metrics == 0 {
+            return Err("truetype font is corrupt".to_string());
+        }
+        Ok(font)
+    }
+
+    /// Return the scale that turns font units into pixels for a font that is `size` pixels tall.
+    pub fn scale_for_size(&self, size: f32) -> f32 {
+        size / self.units_per_em as f32
+    }
+
+    /// Return how far the font reaches above and below the baseline, and the suggested gap
+    /// between lines, in font units. The descent is usually negative.
+    pub fn vertical_metrics(&self) -> (i16, i16, i16) {
+        (self.ascent, self.descent, self.line_gap)
+    }
+
+    /// Return the glyph that draws `ch`, or 0 (the "missing" glyph) if there is none.
+    pub fn glyph_index(&self, ch: char) -> u16 {
+        let result = if self.cmap_format == 12 {
+            self.glyph_index_12(ch as u32)
+        } else {
+            self.glyph_index_4(ch as u32)
+        };
+        match result {
+            Ok(glyph) if glyph < self.num_glyphs => glyph,
+            _ => 0,
+        }
+    }
+
+    fn glyph_index_4(&self, ch: u32) -> Result<u16, String> {
+        if ch > 0xffff {
+            return Ok(0);
+        }
+        let data = &self.data[..];
+        let seg_count = read_u16(data, self.cmap + 6)? as usize / 2;
+        let end_codes = self.cmap + 14;
+        let start_codes = end_codes + seg_count * 2 + 2;
+        let id_deltas = start_codes + seg_count * 2;
+        let id_range_offsets = id_deltas + seg_count * 2;
+        for i in 0..seg_count {
+            if (read_u16(data, end_codes + i * 2)? as u32) < ch {
+                continue;
+            }
+            let start = read_u16(data, start_codes + i * 2)? as u32;
+            if start > ch {
+                return Ok(0);
+            }
+            let delta = read_u16(data, id_deltas + i * 2)?;
+            let range_offset = read_u16(data, id_range_offsets + i * 2)? as usize;
+            if range_offset == 0 {
+                return Ok((ch as u16).wrapping_add(delta));
+            }
+            // The offset is relative to where it is stored itself.
+            let address = id_range_offsets + i * 2 + range_offset + (ch - start) as usize * 2;
+            let glyph = read_u16(data, address)?;
+            return Ok(if glyph == 0 {
+                0
+            } else {
+                glyph.wrapping_add(delta)
+            });
+        }
+        Ok(0)
+    }
+
+    fn glyph_index_12(&self, ch: u32) -> Result<u16, String> {
+        let data = &self.data[..];
+        let num_groups = read_u32(data, self.cmap + 12)? as usize;
+        for i in 0..num_groups {
+            let group = self.cmap + 16 + i * 12;
+            let start = read_u32(data, group)?;
+            let end = read_u32(data, group + 4)?;
+            if start <= ch && ch <= end {
+                // A corrupt group may point past the last glyph, which is as good as missing.
+                let glyph = read_u32(data, group + 8)?.checked_add(ch - start);
+                return Ok(glyph.and_then(|g| u16::try_from(g).ok()).unwrap_or(0));
+            }
+        }
+        Ok(0)
+    }
+
+    /// Return how far the pen moves after drawing the glyph, in font units.
+    pub fn advance(&self, glyph: u16) -> u16 {
+        let metric = glyph.min(self.num_h_metrics - 1) as usize;
+        read_u16(&self.data, self.hmtx + metric * 4).unwrap_or(0)
+    }
+
+    /// Return the outline of the glyph. Glyphs without an outline, like spaces, return nothing.
+    pub fn outline(&self, glyph: u16) -> Vec<Segment> {
+        let mut segments = vec![];
+        // A broken glyph is drawn as whatever could be read of it.
+        let _ = self.add_outline(glyph, [1.0, 0.0, 0.0, 1.0, 0.0, 0.0], 0, &mut segments);
+        segments
+    }
+
+    /// Return where the glyph's data is in the `glyf` table, or None if it is empty.
+    fn glyph_range(&self, glyph: u16) -> Result<Option<(usize, usize)>, String> {
+        let data = &self.data[..];
+        let glyph = glyph as usize;
+        let (start, end) = if self.index_to_loc_format == 0 {
+            (
+                read_u16(data, self.loca + glyph * 2)? as usize * 2,
+                read_u16(data, self.loca + glyph * 2 + 2)? as usize * 2,
+            )
+        } else {
+            (
+                read_u32(data, self.loca + glyph * 4)? as usize,
+                read_u32(data, self.loca + glyph * 4 + 4)? as usize,
+            )
+        };
+        if start >= end {
+            return Ok(None);
+        }
+        Ok(Some((self.glyf + start, self.glyf + end)))
+    }
+
+    /// Add the outline of the glyph to `segments`, after applying the affine `matrix`
+    /// `[a, b, c, d, x, y]` to it.
+    fn add_outline(
+        &self,
+        glyph: u16,
+        matrix: [f32; 6],
+        depth: u32,
+        segments: &mut Vec<Segment>,
+    ) -> Result<(), String> {
+        if glyph >= self.num_glyphs || depth > 8 {
+            return Ok(());
+        }
+        let (start, end) = match self.glyph_range(glyph)? {
+            Some(range) => range,
+            None => return Ok(()),
+        };
+        let data = self
+            .data
+            .get(start..end)
+            .ok_or_else(|| "truetype glyph is out of bounds".to_string())?;
+        let num_contours = read_i16(data, 0)?;
+        if num_contours < 0 {
+            return self.add_composite_outline(data, matrix, depth, segments);
+        }
+
+        let num_contours = num_contours as usize;
+        let mut contour_ends = vec![];
+        for i in 0..num_contours {
+            contour_ends.push(read_u16(data, 10 + i * 2)? as usize);
+        }
+        let num_points = contour_ends.last().map_or(0, |&last| last + 1);
+        let instructions = read_u16(data, 10 + num_contours * 2)? as usize;
+        let mut position = 12 + num_contours * 2 + instructions;
+
+        let mut flags = Vec::with_capacity(num_points);
+        while flags.len() < num_points {
+            let flag = *data
+                .get(position)
+                .ok_or("truetype glyph ends unexpectedly")?;
+            position += 1;
+            flags.push(flag);
+            if flag & 8 != 0 {
+                let repeat = *data
+                    .get(position)
+                    .ok_or("truetype glyph ends unexpectedly")?;
+                position += 1;
+                for _ in 0..repeat {
+                    flags.push(flag);
+                }
+            }
+        }
+        flags.truncate(num_points);
+
+        // all x coordinates come first, then all y coordinates
+        let xs = read_coordinates(data, &mut position, &flags, 2, 16)?;
+        let ys = read_coordinates(data, &mut position, &flags, 4, 32)?;
+
+        let mut first = 0;
+        for &last in &contour_ends {
+            if last < first || last >= num_points {
+                break;
+            }
+            let points: Vec<((f32, f32), bool)> = (first..=last)
+                .map(|i| (transform_point(&matrix, xs[i], ys[i]), flags[i] & 1 != 0))
+                .collect();
+            add_contour(&points, segments);
+            first = last + 1;
+        }
+        Ok(())
+    }
+
+    fn add_composite_outline(
+        &self,
+        data: &[u8],
+        matrix: [f32; 6],
+        depth: u32,
+        segments: &mut Vec<Segment>,
+    ) -> Result<(), String> {
+        const ARGS_ARE_WORDS: u16 = 0x1;
+        const ARGS_ARE_XY_VALUES: u16 = 0x2;
+        const HAVE_SCALE: u16 = 0x8;
+        const MORE_COMPONENTS: u16 = 0x20;
+        const HAVE_X_AND_Y_SCALE: u16 = 0x40;
+        const HAVE_TWO_BY_TWO: u16 = 0x80;
+
+        let mut position = 10;
+        loop {
+            let flags = read_u16(data, position)?;
+            let glyph = read_u16(data, position + 2)?;
+            position += 4;
+            let (x, y) = if flags & ARGS_ARE_WORDS != 0 {
+                position += 4;
+                (
+                    read_i16(data, position - 4)? as f32,
+                    read_i16(data, position - 2)? as f32,
+                )
+            } else {
+                position += 2;
+                let b = data
+                    .get(position - 2..position)
+                    .ok_or("truetype glyph ends unexpectedly")?;
+                (b[0] as i8 as f32, b[1] as i8 as f32)
+            };
+            // Components positioned by matching points up are rare, and left where they are.
+            let (x, y) = if flags & ARGS_ARE_XY_VALUES != 0 {
+                (x, y)
+            } else {
+                (0.0, 0.0)
+            };
+
+            let f2dot14 = |offset: usize| -> Result<f32, String> {
+                Ok(read_i16(data, offset)? as f32 / 16384.0)
+            };
+            let (mut a, mut b, mut c, mut d) = (1.0, 0.0, 0.0, 1.0);
+            if flags & HAVE_SCALE != 0 {
+                a = f2dot14(position)?;
+                d = a;
+                position += 2;
+            } else if flags & HAVE_X_AND_Y_SCALE != 0 {
+                a = f2dot14(position)?;
+                d = f2dot14(position + 2)?;
+                position += 4;
+            } else if flags & HAVE_TWO_BY_TWO != 0 {
+                a = f2dot14(position)?;
+                b = f2dot14(position + 2)?;
+                c = f2dot14(position + 4)?;
+                d = f2dot14(position + 6)?;
+                position += 8;
+            }
+
+            // apply the component's own matrix first, then the parent's
+            let (m, n) = (&matrix, [a, b, c, d, x, y]);
+            let combined = [
+                m[0] * n[0] + m[2] * n[1],
+                m[1] * n[0] + m[3] * n[1],
+                m[0] * n[2] + m[2] * n[3],
+                m[1] * n[2] + m[3] * n[3],
+                m[0] * n[4] + m[2] * n[5] + m[4],
+                m[1] * n[4] + m[3] * n[5] + m[5],
+            ];
+            self.add_outline(glyph, combined, depth + 1, segments)?;
+
+            if flags & MORE_COMPONENTS == 0 {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Read one coordinate of every point of a simple glyph. They are stored as deltas from the
+/// previous point, either as a byte whose sign is in the flags, or as a signed word. `short` and
+/// `same_or_positive` are the flag bits that choose between them.
+fn read_coordinates(
+    data: &[u8],
+    position: &mut usize,
+    flags: &[u8],
+    short: u8,
+    same_or_positive: u8,
+) -> Result<Vec<f32>, String> {
+    let mut value = 0i32;
+    let mut coordinates = Vec::with_capacity(flags.len());
+    for &flag in flags {
+        if flag & short != 0 {
+            let delta = *data
+                .get(*position)
+                .ok_or("truetype glyph ends unexpectedly")? as i32;
+            *position += 1;
+            value += if flag & same_or_positive != 0 {
+                delta
+            } else {
+                -delta
+            };
+        } else if flag & same_or_positive == 0 {
+            value += read_i16(data, *position)? as i32;
+            *position += 2;
+        }
+        coordinates.push(value as f32);
+    }
+    Ok(coordinates)
+}
+
+fn transform_point(matrix: &[f32; 6], x: f32, y: f32) -> (f32, f32) {
+    (
+        matrix[0] * x + matrix[2] * y + matrix[4],
+        matrix[1] * x + matrix[3] * y + matrix[5],
+    )
+}
+
+fn midpoint(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+}
+
+/// Turn one closed contour of on-curve and off-curve points into segments. Two off-curve points in
+/// a row have an implied on-curve point halfway between them.
+fn add_contour(points: &[((f32, f32), bool)], segments: &mut Vec<Segment>) {
+    let count = points.len();
+    if count == 0 {
+        return;
+    }
+    // Start on a point that is on the curve. If there are none, start halfway between the last
+    // point and the first one.
+    let (start, first) = match points.iter().position(|&(_, on)| on) {
+        Some(i) => (points[i].0, i),
+        None => (midpoint(points[count - 1].0, points[0].0), count - 1),
+    };
+
+    let mut current = start;
+    let mut control: Option<(f32, f32)> = None;
+    for i in 1..=count {
+        let (point, on) = points[(first + i) % count];
+        match (control, on) {
+            (None, true) => {
+                segments.push(Segment::Line(current, point));
+                current = point;
+            }
+            (None, false) => control = Some(point),
+            (Some(c), true) => {
+                segments.push(Segment::Quad(current, c, point));
+                current = point;
+                control = None;
+            }
+            (Some(c), false) => {
+                let middle = midpoint(c, point);
+                segments.push(Segment::Quad(current, c, middle));
+                current = middle;
+                control = Some(point);
+            }
+        }
+    }
+    if let Some(c) = control {
+        segments.push(Segment::Quad(current, c, start));
+    }
+}
+
+/// Turn an outline into pixels, scaling font units by `scale`. Pixels are covered according to
+/// the nonzero winding rule, with antialiasing.
+pub fn rasterize(outline: &[Segment], scale: f32) -> GlyphBitmap {
+    let mut lines = vec![];
+    for segment in outline {
+        match *segment {
+            Segment::Line(a, b) => lines.push((a, b)),
+            Segment::Quad(a, c, b) => flatten_quad(a, c, b, scale, &mut lines),
+        }
+    }
+    if lines.is_empty() {
+        return GlyphBitmap {
+            width: 0,
+            height: 0,
+            offset: (0, 0),
+            coverage: vec![],
+        };
+    }
+
+    // flip y, so that it points down like on the screen
+    let lines: Vec<Line> = lines
+        .iter()
+        .map(|&(a, b)| ((a.0 * scale, -a.1 * scale), (b.0 * scale, -b.1 * scale)))
+        .collect();
+    let (mut left, mut top) = (f32::MAX, f32::MAX);
+    let (mut right, mut bottom) = (f32::MIN, f32::MIN);
+    for &(a, b) in &lines {
+        for &(x, y) in &[a, b] {
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x);
+            bottom = bottom.max(y);
+        }
+    }
+    let (left, top) = (left.floor(), top.floor());
+    // one column to spare, because coverage can spill into the pixel after the right edge
+    let width = (right.ceil() - left) as usize + 1;
+    let height = (bottom.ceil() - top) as usize;
+
+    let mut accumulation = vec![0.0f32; width * height + 4];
+    for &(a, b) in &lines {
+        accumulate_line(
+            &mut accumulation,
+            width,
+            height,
+            (a.0 - left, a.1 - top),
+            (b.0 - left, b.1 - top),
+        );
+    }
+
+    // Each cell holds how much the coverage changes from the pixel before it, so add them up.
+    let mut total = 0.0;
+    let coverage = accumulation[..width * height]
+        .iter()
+        .map(|&change| {
+            total += change;
+            (total.abs().min(1.0) * 255.0).round() as u8
+        })
+        .collect();
+    GlyphBitmap {
+        width: width as u32,
+        height: height as u32,
+        offset: (left as i32, top as i32),
+        coverage,
+    }
+}
+
+/// Split a quadratic curve into lines short enough to look smooth after scaling.
+fn flatten_quad(a: (f32, f32), c: (f32, f32), b: (f32, f32), scale: f32, lines: &mut Vec<Line>) {
+    let deviation_x = (a.0 - 2.0 * c.0 + b.0) * scale;
+    let deviation_y = (a.1 - 2.0 * c.1 + b.1) * scale;
+    let deviation = deviation_x * deviation_x + deviation_y * deviation_y;
+    let steps = 1 + (3.0 * deviation).sqrt().sqrt().floor() as usize;
+    let mut previous = a;
+    for step in 1..=steps {
+        let t = step as f32 / steps as f32;
+        let u = 1.0 - t;
+        let point = (
+            u * u * a.0 + 2.0 * u * t * c.0 + t * t * b.0,
+            u * u * a.1 + 2.0 * u * t * c.1 + t * t * b.1,
+        );
+        lines.push((previous, point));
+        previous = point;
+    }
+}
+
+/// Add the signed area that the line covers in each pixel to `accumulation`, as the change from
+/// the pixel before. Lines going down add coverage and lines going up take it away, so a closed
+/// outline adds up to its winding number inside and zero outside.
+fn accumulate_line(
+    accumulation: &mut [f32],
+    width: usize,
+    height: usize,
+    p0: (f32, f32),
+    p1: (f32, f32),
+) {
+    if p0.1 == p1.1 {
+        return;
+    }
+    let (direction, p0, p1) = if p0.1 < p1.1 {
+        (1.0, p0, p1)
+    } else {
+        (-1.0, p1, p0)
+    };
+    let dxdy = (p1.0 - p0.0) / (p1.1 - p0.1);
+    let mut x = p0.0;
+    let first_row = p0.1.max(0.0) as usize;
+    if p0.1 < 0.0 {
+        x -= p0.1 * dxdy;
+    }
+    let last_row = (p1.1.ceil() as usize).min(height);
+
+    for row in first_row..last_row {
+        let start = row * width;
+        let dy = (row as f32 + 1.0).min(p1.1) - (row as f32).max(p0.1);
+        let x_next = x + dxdy * dy;
+        let d = dy * direction;
+        let (x0, x1) = if x < x_next { (x, x_next) } else { (x_next, x) };
+        let x0_floor = x0.floor();
+        let x0_index = x0_floor as usize;
+        let x1_ceil = x1.ceil();
+        let x1_index = x1_ceil as usize;
+
+        if x1_index <= x0_index + 1 {
+            // the line stays within one pixel on this row
+            let middle = 0.5 * (x + x_next) - x0_floor;
+            accumulation[start + x0_index] += d - d * middle;
+            accumulation[start + x0_index + 1] += d * middle;
+        } else {
+            let s = 1.0 / (x1 - x0);
+            let x0_fraction = x0 - x0_floor;
+            let a0 = 0.5 * s * (1.0 - x0_fraction) * (1.0 - x0_fraction);
+            let x1_fraction = x1 - x1_ceil + 1.0;
+            let am = 0.5 * s * x1_fraction * x1_fraction;
+            accumulation[start + x0_index] += d * a0;
+            if x1_index == x0_index + 2 {
+                accumulation[start + x0_index + 1] += d * (1.0 - a0 - am);
+            } else {
+                let a1 = s * (1.5 - x0_fraction);
+                accumulation[start + x0_index + 1] += d * (a1 - a0);
+                for column in x0_index + 2..x1_index - 1 {
+                    accumulation[start + column] += d * s;
+                }
+                let a2 = a1 + (x1_index - x0_index - 3) as f32 * s;
+                accumulation[start + x1_index - 1] += d * (1.0 - a2 - am);
+            }
+            accumulation[start + x1_index] += d * am;
+        }
+        x = x_next;
+    }
+}
+
+/// Build a font with two glyphs: an empty "missing" glyph, and a square with a square hole in it
+/// for 'o'.
+#[cfg(test)]
+fn test_font() -> Vec<u8> {
+    fn table(tag: &[u8], data: Vec<u8>) -> (Vec<u8>, Vec<u8>) {
+        (tag.to_vec(), data)
+    }
+
+    let mut head = vec![0; 54];
+    head[18..20].copy_from_slice(&1000u16.to_be_bytes()); // units per em
+    head[50..52].copy_from_slice(&0i16.to_be_bytes()); // short loca offsets
+
+    let mut hhea = vec![0; 36];
+    hhea[4..6].copy_from_slice(&800i16.to_be_bytes());
+    hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+    hhea[34..36].copy_from_slice(&2u16.to_be_bytes());
+
+    let mut maxp = vec![0; 6];
+    maxp[4..6].copy_from_slice(&2u16.to_be_bytes());
+
+    let mut hmtx = vec![];
+    for &advance in &[500u16, 600] {
+        hmtx.extend_from_slice(&advance.to_be_bytes());
+        hmtx.extend_from_slice(&0i16.to_be_bytes());
+    }
+
+    // outer square clockwise, inner square counterclockwise, all points on the curve
+    let points: [(i16, i16); 8] = [
+        (0, 0),
+        (0, 500),
+        (500, 500),
+        (500, 0),
+        (100, 100),
+        (400, 100),
+        (400, 400),
+        (100, 400),
+    ];
+    let mut glyf = vec![];
+    glyf.extend_from_slice(&2i16.to_be_bytes());
+    glyf.extend_from_slice(&[0; 8]); // bounding box
+    glyf.extend_from_slice(&3u16.to_be_bytes());
+    glyf.extend_from_slice(&7u16.to_be_bytes());
+    glyf.extend_from_slice(&0u16.to_be_bytes()); // no instructions
+    glyf.extend_from_slice(&[1; 8]); // on curve, long coordinates
+    for axis in 0..2 {
+        let mut previous = 0;
+        for point in &points {
+            let value = if axis == 0 { point.0 } else { point.1 };
+            glyf.extend_from_slice(&(value - previous).to_be_bytes());
+            previous = value;
+        }
+    }
+
+    let mut loca = vec![];
+    for &offset in &[0u16, 0, glyf.len() as u16 / 2] {
+        loca.extend_from_slice(&offset.to_be_bytes());
+    }
+
+    // format 4, with a segment for 'o' and the final 0xffff segment
+    let mut cmap = vec![0, 0, 0, 1, 0, 3, 0, 1, 0, 0, 0, 12];
+    let segments: [u16; 6] = [0x6f, 0xffff, 0x6f, 0xffff, 1u16.wrapping_sub(0x6f), 1];
+    cmap.extend_from_slice(&4u16.to_be_bytes());
+    cmap.extend_from_slice(&32u16.to_be_bytes()); // length
+    cmap.extend_from_slice(&0u16.to_be_bytes());
+    cmap.extend_from_slice(&4u16.to_be_bytes()); // segment count * 2
+    cmap.extend_from_slice(&[0; 6]);
+    for (i, value) in segments.iter().enumerate() {
+        cmap.extend_from_slice(&value.to_be_bytes());
+        if i == 1 {
+            cmap.extend_from_slice(&[0, 0]); // reserved padding
+        }
+    }
+    cmap.extend_from_slice(&[0; 4]); // id range offsets
+
+    let tables = vec![
+        table(b"cmap", cmap),
+        table(b"glyf", glyf),
+        table(b"head", head),
+        table(b"hhea", hhea),
+        table(b"hmtx", hmtx),
+        table(b"loca", loca),
+        table(b"maxp", maxp),
+    ];
+    let mut font = vec![0, 1, 0, 0];
+    font.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+    font.extend_from_slice(&[0; 6]);
+    let mut offset = 12 + tables.len() * 16;
+    for (tag, data) in &tables {
+        font.extend_from_slice(tag);
+        font.extend_from_slice(&[0; 4]);
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len();
+    }
+    for (_, data) in &tables {
+        font.extend_from_slice(data);
+    }
+    font
+}
+
+#[test]
+fn truetype_metrics() {
+    let font = TrueType::parse(&test_font()).unwrap();
+    assert_eq!(font.glyph_index('o'), 1);
+    assert_eq!(font.glyph_index('p'), 0);
+    assert_eq!(font.glyph_index('\u{1f600}'), 0);
+    assert_eq!(font.advance(1), 600);
+    assert_eq!(font.vertical_metrics(), (800, -200, 0));
+    assert_eq!(font.scale_for_size(20.0), 0.02);
+    assert_eq!(font.outline(0), vec![]);
+    assert_eq!(font.outline(1).len(), 8);
+}
+
+#[test]
+fn truetype_format_12() {
+    let mut font = TrueType::parse(&test_font()).unwrap();
+    let mut cmap = vec![];
+    cmap.extend_from_slice(&12u16.to_be_bytes());
+    cmap.extend_from_slice(&[0; 10]); // reserved, length, language
+    let groups = [('o', 'o', 1), ('p', 'q', u32::MAX), ('r', 'r', 0x1_0001)];
+    cmap.extend_from_slice(&(groups.len() as u32).to_be_bytes());
+    for &(start, end, glyph) in &groups {
+        cmap.extend_from_slice(&(start as u32).to_be_bytes());
+        cmap.extend_from_slice(&(end as u32).to_be_bytes());
+        cmap.extend_from_slice(&glyph.to_be_bytes());
+    }
+    font.cmap = font.data.len();
+    font.cmap_format = 12;
+    font.data.extend_from_slice(&cmap);
+
+    assert_eq!(font.glyph_index('o'), 1);
+    // glyphs past the end of the font, or past what a u16 holds, are missing
+    assert_eq!(font.glyph_index('p'), 0);
+    assert_eq!(font.glyph_index('q'), 0);
+    assert_eq!(font.glyph_index('r'), 0);
+    assert_eq!(font.glyph_index('s'), 0);
+}
+
+#[test]
+fn truetype_rasterize() {
+    let font = TrueType::parse(&test_font()).unwrap();
+    let bitmap = rasterize(&font.outline(1), 0.02);
+
+    // 10 pixels square, standing on the baseline, plus the spare column
+    assert_eq!((bitmap.width, bitmap.height), (11, 10));
+    assert_eq!(bitmap.offset, (0, -10));
+    let at = |x: usize, y: usize| bitmap.coverage[y * 11 + x];
+    assert_eq!(at(0, 0), 255);
+    assert_eq!(at(9, 9), 255);
+    assert_eq!(at(5, 5), 0); // the hole
+    assert_eq!(at(10, 5), 0);
+
+    let bitmap = rasterize(&font.outline(1), 0.015);
+    // the edge at 7.5 pixels covers half of the pixels next to it
+    assert_eq!(bitmap.width, 9);
+    assert_eq!(bitmap.coverage[9 + 7], 128);
+}
+
+#[test]
+fn truetype_rejects_garbage() {
+    assert!(TrueType::parse(&[]).is_err());
+    assert!(TrueType::parse(b"OTTO\0\0\0\0\0\0\0\0").is_err());
+    assert!(TrueType::parse(&test_font()[..100]).is_err());
+}
+
+#[test]
+fn truetype_off_curve_points() {
+    // two off-curve points in a row get an implied point halfway between them
+    let mut segments = vec![];
+    add_contour(
+        &[
+            ((0.0, 0.0), true),
+            ((0.0, 10.0), false),
+            ((10.0, 10.0), false),
+        ],
+        &mut segments,
+    );
+    assert_eq!(
+        segments,
+        vec![
+            Segment::Quad((0.0, 0.0), (0.0, 10.0), (5.0, 10.0)),
+            Segment::Quad((5.0, 10.0), (10.0, 10.0), (0.0, 0.0)),
+        ]
+    );
+}
@@ -37,6 +37,24 @@ fn dup_chars() {
     ));
 }
 
+/// Return how many bytes `width * height` RGBA pixels take up, or None if that doesn't fit in
+/// memory.
+pub fn rgba_len(width: u32, height: u32) -> Option<usize> {
+    (width as usize)
+        .checked_mul(height as usize)?
+        .checked_mul(4)
+}
+
+#[test]
+fn rgba_lengths() {
+    assert_eq!(rgba_len(0, 100), Some(0));
+    assert_eq!(rgba_len(3, 2), Some(24));
+    assert_eq!(
+        rgba_len(u32::MAX, u32::MAX),
+        (u32::MAX as usize).checked_mul(u32::MAX as usize * 4)
+    );
+}
+
 /// Utility method to set the texture's color and alpha mods to the Color.
 pub fn set_texture_color(color: &pixels::Color, texture: &mut render::Texture) {
     // configure the texture for drawing according to the current foreground_color
@@ -1,20 +1,39 @@
 use std;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ops::Range;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 extern crate sdl2;
 use sdl2::image::ImageRWops;
 use sdl2::image::LoadSurface;
-use sdl2::image::LoadTexture;
 use sdl2::pixels;
 use sdl2::render;
 use sdl2::rwops;
 use sdl2::surface;
+use sdl2::sys;
 
+use animation::Animation;
+use aseprite::{self, AsepriteFile};
+use batch::{self, Batch, BatchMode};
+use blend::{self, BlendMode};
+use bmfont;
+use color::Color;
 use event::{self, Event};
+use geometry::{self, Vertex};
+use gradient::Gradient;
+use markup::{self, Piece};
 use shape;
+use sprite::{Sprite, SpriteKey, SpriteSheet};
+use text::{self, Align, Line, Metrics, PrintOptions, TextOptions, VerticalAlign};
+use transform::Transform;
+use truetype;
 use util;
 
+use serde_json::{self, Map, Value};
+
 /**
  * A Window can display graphics and handle events.
  *
@@ -30,7 +49,14 @@ pub struct Window {
     timer_subsystem: sdl2::TimerSubsystem,
     canvas: sdl2::render::Canvas<sdl2::video::Window>,
     foreground_color: pixels::Color,
+    blend_mode: BlendMode,
     font: Option<Font>,
+    transform: Transform,
+    transform_stack: Vec<Transform>,
+    logical_size: Option<(u32, u32, ScaleMode)>,
+    clip_stack: Vec<Option<shape::Rect>>,
+    batch: Option<Batch>,
+    layer: i32,
 
     // events and event logic
     running: bool,
@@ -39,6 +65,7 @@ pub struct Window {
     // timing
     target_ticks_per_frame: u32,
     ticks_at_previous_frame: u32,
+    frame_time: u32,
 }
 
 /// Top-level Running / Creation Methods
@@ -68,10 +95,10 @@ impl Window {
         let event_pump = sdl_context.event_pump().unwrap();
         let sdl_window_builder = video_subsystem.window(name, width as u32, height as u32);
         let sdl_window = sdl_window_builder.build().unwrap();
-        let mut canvas = sdl_window.into_canvas().build().unwrap();
+        let mut canvas = sdl_window.into_canvas().target_texture().build().unwrap();
 
         // for transparency
-        canvas.set_blend_mode(render::BlendMode::Blend);
+        blend::set_draw_blend_mode(&mut canvas, BlendMode::Alpha).unwrap();
 
         let mut window = Window {
             timer_subsystem: timer_subsystem,
@@ -80,15 +107,26 @@ impl Window {
             running: true,
             event_queue: vec![],
             foreground_color: pixels::Color::RGBA(0, 0, 0, 255),
+            blend_mode: BlendMode::Alpha,
             target_ticks_per_frame: (1000.0 / 60.0) as u32,
             ticks_at_previous_frame: 0,
+            frame_time: 0,
             font: None,
+            transform: Transform::identity(),
+            transform_stack: vec![],
+            logical_size: None,
+            clip_stack: vec![],
+            batch: None,
+            layer: 0,
         };
 
+        // Start timing from here, so that the first frame doesn't include SDL's startup time.
+        window.ticks_at_previous_frame = window.timer_subsystem.ticks();
+
         // clear first, then load the default font
         window.clear();
         window.canvas.present();
-        window.set_color(255, 255, 255, 255);
+        window.set_color((255, 255, 255, 255));
 
         // load the default font
         let font = window
@@ -109,13 +147,20 @@ impl Window {
             return false;
         }
 
+        self.flush_batch();
         self.canvas.present();
 
+        // Stretching depends on the size of the Window, which may have changed since last frame.
+        if let Some((_, _, ScaleMode::Stretch)) = self.logical_size {
+            self.apply_logical_size();
+        }
+
         let mut current_ticks = self.timer_subsystem.ticks();
         while current_ticks - self.ticks_at_previous_frame < self.target_ticks_per_frame {
             self.timer_subsystem.delay(3);
             current_ticks = self.timer_subsystem.ticks();
         }
+        self.frame_time = current_ticks - self.ticks_at_previous_frame;
         self.ticks_at_previous_frame = current_ticks;
 
         // Handle events
@@ -126,6 +171,22 @@ impl Window {
                 Some(sdl_event) => match Event::from_sdl2_event(sdl_event) {
                     Some(Event::Quit) => self.quit(),
 
+                    // SDL only maps mouse coordinates for the modes that use its own logical size
+                    Some(Event::Mouse {
+                        is_down,
+                        button,
+                        mouse_x,
+                        mouse_y,
+                    }) if self.is_stretched() => {
+                        let (mouse_x, mouse_y) = self.window_to_logical(mouse_x, mouse_y);
+                        self.event_queue.push(Event::Mouse {
+                            is_down,
+                            button,
+                            mouse_x,
+                            mouse_y,
+                        });
+                    }
+
                     // any other unrecognized event
                     Some(e) => (self.event_queue.push(e)),
                     None => (),
@@ -136,6 +197,13 @@ impl Window {
         true
     }
 
+    /// Return the number of milliseconds that passed between the last two calls to `next_frame()`.
+    /// This is what you should pass to `Animation::update()` and to anything else that moves at a
+    /// fixed speed, since frames can take longer than expected.
+    pub fn frame_time(&self) -> u32 {
+        self.frame_time
+    }
+
     /// Return true when there is an event waiting in the queue for processing.
     pub fn has_event(&self) -> bool {
         self.event_queue.len() > 0
@@ -165,10 +233,86 @@ impl Window {
         mouse_state.is_mouse_button_pressed(button)
     }
 
-    /// Return the current position of the mouse, relative to the top-left corner of the Window.
+    /// Return the current position of the mouse in the same coordinates that draw calls use. With
+    /// the default transform, that is relative to the top-left corner of the Window. See
+    /// `screen_to_world`.
     pub fn mouse_position(&self) -> (i32, i32) {
         let mouse_state = self.event_pump.mouse_state();
-        (mouse_state.x(), mouse_state.y())
+        let (x, y) = self.window_to_logical(mouse_state.x(), mouse_state.y());
+        let (x, y) = self.screen_to_world(x, y);
+        (x.floor() as i32, y.floor() as i32)
+    }
+
+    /// Draw onto a screen of `width` by `height` pixels, whatever the actual size of the Window,
+    /// and scale it up or down to fill the Window according to `mode`. Mouse positions, both in
+    /// `Event::Mouse` and from `mouse_position`, are converted to match.
+    ///
+    /// This is the easiest way to make a pixel-art game look the same at any window size.
+    pub fn set_logical_size(&mut self, width: u32, height: u32, mode: ScaleMode) {
+        self.logical_size = Some((width, height, mode));
+        self.apply_logical_size();
+    }
+
+    /// Go back to drawing at the actual size of the Window.
+    pub fn clear_logical_size(&mut self) {
+        self.logical_size = None;
+        self.apply_logical_size();
+    }
+
+    /// Return the size set by `set_logical_size`, or None if there isn't one.
+    pub fn get_logical_size(&self) -> Option<(u32, u32)> {
+        self.logical_size.map(|(width, height, _)| (width, height))
+    }
+
+    /// Bring SDL's scaling up to date with `self.logical_size`.
+    fn apply_logical_size(&mut self) {
+        self.flush_batch();
+        let (width, height, mode) = match self.logical_size {
+            Some(size) => size,
+            None => (0, 0, ScaleMode::Fit),
+        };
+        let integer_scale = if mode == ScaleMode::IntegerScale {
+            sys::SDL_bool::SDL_TRUE
+        } else {
+            sys::SDL_bool::SDL_FALSE
+        };
+        unsafe { sys::SDL_RenderSetIntegerScale(self.canvas.raw(), integer_scale) };
+
+        if mode == ScaleMode::Stretch {
+            // SDL's logical size always keeps the aspect ratio, so stretching is done by hand.
+            self.canvas.set_logical_size(0, 0).unwrap();
+            // The scale is in output pixels, which differ from window coordinates on high-DPI
+            // displays.
+            let output_size = self.canvas.window().drawable_size();
+            let (_, _, scale_x, scale_y) = logical_viewport(output_size, (width, height), mode);
+            self.canvas.set_scale(scale_x, scale_y).unwrap();
+        } else {
+            // A logical size of 0x0 turns SDL's scaling off.
+            self.canvas.set_logical_size(width, height).unwrap();
+        }
+    }
+
+    fn is_stretched(&self) -> bool {
+        matches!(self.logical_size, Some((_, _, ScaleMode::Stretch)))
+    }
+
+    /// Return where the logical screen sits in the Window: the offset of its top-left corner and
+    /// its scale in each direction.
+    fn logical_viewport(&self) -> (f32, f32, f32, f32) {
+        let window_size = self.canvas.window().size();
+        match self.logical_size {
+            Some((width, height, mode)) => logical_viewport(window_size, (width, height), mode),
+            None => (0.0, 0.0, 1.0, 1.0),
+        }
+    }
+
+    /// Convert a position in the Window into a position on the logical screen.
+    fn window_to_logical(&self, x: i32, y: i32) -> (i32, i32) {
+        let (offset_x, offset_y, scale_x, scale_y) = self.logical_viewport();
+        (
+            ((x as f32 - offset_x) / scale_x).floor() as i32,
+            ((y as f32 - offset_y) / scale_y).floor() as i32,
+        )
     }
 
     /// Use this Font for future calls to `print()`.
@@ -188,289 +332,3359 @@ impl Window {
 impl Window {
     /// Windows have a color set on them at all times. This color is applied to every draw
     /// operation. To "unset" the color, call set_color with (255,255,255,255)
-    pub fn set_color(&mut self, red: u8, green: u8, blue: u8, alpha: u8) {
-        self.foreground_color = pixels::Color::RGBA(red, green, blue, alpha);
+    ///
+    /// `color` may be a Color or a tuple of `(red, green, blue, alpha)` or `(red, green, blue)`.
+    pub fn set_color<C: Into<Color>>(&mut self, color: C) {
+        self.foreground_color = color.into().into();
+    }
+
+    /// Return the color set by `set_color`.
+    pub fn get_color(&self) -> Color {
+        self.foreground_color.into()
+    }
+
+    /// Choose how everything drawn from now on is combined with what is already on the screen.
+    /// This applies to shapes, images and text alike. See `BlendMode`.
+    ///
+    /// `Multiply` and `Premultiplied` need a renderer that supports custom blend modes, which the
+    /// software renderer doesn't. If the renderer can't use `mode`, this returns an error and the
+    /// BlendMode stays as it was.
+    pub fn set_blend_mode(&mut self, mode: BlendMode) -> Result<(), String> {
+        blend::set_draw_blend_mode(&mut self.canvas, mode)?;
+        self.blend_mode = mode;
+        Ok(())
+    }
+
+    /// Return the BlendMode set by `set_blend_mode`.
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Return the color that draw calls should use. This is the draw color, premultiplied if the
+    /// BlendMode asks for it.
+    fn draw_color(&self) -> pixels::Color {
+        self.blend_color(self.foreground_color)
+    }
+
+    /// Return the color to draw with in place of `color`, premultiplied if the BlendMode asks for
+    /// it.
+    fn blend_color(&self, color: pixels::Color) -> pixels::Color {
+        match self.blend_mode {
+            BlendMode::Premultiplied => Color::from(color).premultiply().into(),
+            _ => color,
+        }
+    }
+
+    /// Set up the texture to be drawn with the current color and BlendMode.
+    fn prepare_texture(&self, texture: &mut render::Texture) {
+        util::set_texture_color(&self.draw_color(), texture);
+        self.apply_blend_mode(texture);
+    }
+
+    /// Use the Window's BlendMode whenever `texture` is drawn.
+    fn apply_blend_mode(&self, texture: &mut render::Texture) {
+        // `set_blend_mode` only accepts modes that the renderer supports, and textures support
+        // the same ones, so there is nothing to do if this fails.
+        blend::set_texture_blend_mode(texture, self.blend_mode).ok();
     }
 
     /// Set up the color according to the internal state of the Window.
     fn prepare_to_draw(&mut self) {
-        self.canvas.set_draw_color(self.foreground_color);
+        let color = self.draw_color();
+        self.canvas.set_draw_color(color);
     }
 
-    // These functions are just aliases onto self.canvas.as you can see.
+    // These functions are just aliases onto self.canvas, unless a transform is set.
     pub fn draw_rect(&mut self, rect: shape::Rect) {
         self.prepare_to_draw();
-        self.canvas.draw_rect(rect).unwrap();
+        if self.transform.is_identity() && self.batch.is_none() {
+            self.canvas.draw_rect(rect).unwrap();
+            return;
+        }
+        // Outline the pixels just inside the rectangle, the same way SDL does.
+        let (x, y) = (rect.x() as f32 + 0.5, rect.y() as f32 + 0.5);
+        let (w, h) = (rect.width() as f32 - 1.0, rect.height() as f32 - 1.0);
+        let corners = [(x, y), (x + w, y), (x + w, y + h), (x, y + h), (x, y)];
+        let points: Vec<shape::Point> = corners
+            .iter()
+            .map(|&(x, y)| {
+                let (x, y) = self.transform.apply(x, y);
+                shape::Point::new(x.floor() as i32, y.floor() as i32)
+            })
+            .collect();
+        self.draw_line_strip(&points);
     }
     pub fn fill_rect(&mut self, rect: shape::Rect) {
         self.prepare_to_draw();
-        self.canvas.fill_rect(rect).unwrap();
+        if self.transform.is_identity() && self.batch.is_none() {
+            self.canvas.fill_rect(rect).unwrap();
+            return;
+        }
+        self.fill_transformed_rect(
+            rect.x() as f32,
+            rect.y() as f32,
+            rect.width() as f32,
+            rect.height() as f32,
+        );
     }
     pub fn draw_point(&mut self, point: shape::Point) {
         self.prepare_to_draw();
-        self.canvas.draw_point(point).unwrap();
+        if self.transform.is_identity() {
+            self.draw_pixels(&[point]);
+            return;
+        }
+        // A point covers one pixel, which may be more than one pixel once it is zoomed in.
+        self.fill_rect(shape::Rect::new(point.x(), point.y(), 1, 1));
     }
     pub fn draw_polygon(&mut self, polygon: shape::Polygon) {
         self.prepare_to_draw();
-        self.canvas.draw_points(&polygon[..]).unwrap();
+        if self.transform.is_identity() {
+            self.draw_pixels(&polygon[..]);
+            return;
+        }
+        for &point in &polygon[..] {
+            self.draw_point(point);
+        }
     }
 
-    /// Display the image with its top-left corner at (x, y)
-    pub fn draw_image(&mut self, image: &mut Image, x: i32, y: i32) {
-        // first, configure the texture for drawing according to the current foreground_color
-        util::set_texture_color(&self.foreground_color, &mut image.texture);
-
-        // copy the texture onto the drawer()
-        self.canvas
-            .copy(
-                &(image.texture),
-                Some(shape::Rect::new(
-                    x,
-                    y,
-                    image.get_width() as u32,
-                    image.get_height() as u32,
-                )),
-                None,
-            )
-            .unwrap();
+    /// Draw lines through the points with the draw color, or add them to the batch.
+    fn draw_line_strip(&mut self, points: &[shape::Point]) {
+        let (state, color) = (self.batch_state(), self.draw_color());
+        match self.batch {
+            Some(ref mut batch) => batch.push_lines(state, points, color),
+            None => self.canvas.draw_lines(points).unwrap(),
+        }
     }
 
-    /// Write the text to the screen at (x, y) using the currently set font on the Window. Return a
-    /// Rectangle describing the area of the screen that was modified.
-    // TODO: Implement print_rect that wraps text to fit inside of a Rectangle.
-    pub fn print(&mut self, text: &str, x: i32, y: i32) -> shape::Rect {
-        self.prepare_to_draw();
-        let font = match self.font {
-            Some(ref mut r) => r,
+    /// Draw the pixels at the points with the draw color, or add them to the batch.
+    fn draw_pixels(&mut self, points: &[shape::Point]) {
+        let (state, color) = (self.batch_state(), self.draw_color());
+        match self.batch {
+            Some(ref mut batch) => batch.push_points(state, points, color),
+            None => self.canvas.draw_points(points).unwrap(),
+        }
+    }
 
-            // FIXME: shouldn't be possible to have no font, and the `font` field on Window should
-            // be updated to reflect this.
-            None => panic!("no font set on window"),
+    /// Fill the rectangle with the draw color, passing it through the transform.
+    fn fill_transformed_rect(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        let corners = [
+            self.transform.apply(x, y),
+            self.transform.apply(x + width, y),
+            self.transform.apply(x + width, y + height),
+            self.transform.apply(x, y + height),
+        ];
+        if self.transform.uniform_scale().is_some() && self.batch.is_none() {
+            // Rectangles stay rectangles, so let SDL fill it. Rounding both edges keeps
+            // neighbouring rectangles from overlapping or leaving gaps.
+            let (left, top) = (corners[0].0.round(), corners[0].1.round());
+            let (right, bottom) = (corners[2].0.round(), corners[2].1.round());
+            if right > left && bottom > top {
+                let rect = shape::Rect::new(
+                    left as i32,
+                    top as i32,
+                    (right - left) as u32,
+                    (bottom - top) as u32,
+                );
+                self.canvas.fill_rect(rect).unwrap();
+            }
+            return;
+        }
+        let color = self.draw_color();
+        let vertices: Vec<geometry::RawVertex> = corners
+            .iter()
+            .map(|&(x, y)| geometry::RawVertex::new(x, y, color))
+            .collect();
+        self.fill_triangles(&vertices, &batch::QUAD_INDICES);
+    }
+
+    /// Draw the triangles with the current BlendMode, or add them to the batch.
+    fn fill_triangles(&mut self, vertices: &[geometry::RawVertex], indices: &[i32]) {
+        let state = self.batch_state();
+        match self.batch {
+            Some(ref mut batch) => batch.push(state, None, vertices, indices),
+            None => geometry::render_geometry(&mut self.canvas, None, vertices, indices).unwrap(),
+        }
+    }
+
+    /// Like `fill_triangles`, for triangles that show parts of `texture`.
+    fn fill_textured_triangles(
+        &mut self,
+        texture: &mut render::Texture,
+        vertices: &[geometry::RawVertex],
+        indices: &[i32],
+    ) {
+        let state = self.batch_state();
+        match self.batch {
+            Some(ref mut batch) => batch.push(state, Some(texture), vertices, indices),
+            None => {
+                // The vertices carry the color, so the texture itself must not be tinted.
+                util::set_texture_color(&pixels::Color::RGBA(255, 255, 255, 255), texture);
+                self.apply_blend_mode(texture);
+                geometry::render_geometry(&mut self.canvas, Some(texture), vertices, indices)
+                    .unwrap();
+            }
+        }
+    }
+
+    /// Draw triangles with a color at every vertex, blending smoothly between them. If `image` is
+    /// given, it is stretched over the triangles according to the `u` and `v` of each vertex, and
+    /// tinted by the vertex colors. This is how to draw trails, deformed sprites and gradients of
+    /// any shape.
+    ///
+    /// Every three entries of `indices` pick the vertices of one triangle. If `indices` is empty,
+    /// every three vertices make up one triangle instead. Vertex colors are used as they are,
+    /// instead of the draw color, but the vertices do pass through the transform.
+    ///
+    /// ```rust,no_run
+    /// # let mut app = simple::Window::new("hello world", 640, 480);
+    /// use simple::{Color, Vertex};
+    /// app.draw_mesh(
+    ///     &[
+    ///         Vertex::new(320.0, 100.0, Color::RED),
+    ///         Vertex::new(500.0, 380.0, Color::GREEN),
+    ///         Vertex::new(140.0, 380.0, Color::BLUE),
+    ///     ],
+    ///     &[],
+    ///     None,
+    /// );
+    /// ```
+    ///
+    /// NOTE: This function panics if an index is out of range, or if the number of indices (or of
+    /// vertices, without indices) isn't a multiple of 3.
+    pub fn draw_mesh(&mut self, vertices: &[Vertex], indices: &[u32], image: Option<&mut Image>) {
+        let count = if indices.is_empty() {
+            vertices.len()
+        } else {
+            indices.len()
         };
-        util::set_texture_color(&self.foreground_color, &mut font.texture);
+        assert!(count % 3 == 0, "a mesh must be made of whole triangles");
+        assert!(
+            indices.iter().all(|&i| (i as usize) < vertices.len()),
+            "mesh index out of range"
+        );
 
-        let mut current_x = x;
+        let premultiply = self.blend_mode == BlendMode::Premultiplied;
+        let transformed: Vec<geometry::RawVertex> = vertices
+            .iter()
+            .map(|vertex| {
+                let (x, y) = self.transform.apply(vertex.x, vertex.y);
+                let color = if premultiply {
+                    vertex.color.premultiply()
+                } else {
+                    vertex.color
+                };
+                geometry::RawVertex::textured(x, y, color.into(), vertex.u, vertex.v)
+            })
+            .collect();
+        let indices: Vec<i32> = indices.iter().map(|&i| i as i32).collect();
 
-        for ch in text.chars() {
-            let font_rect = match font.get_rect(ch) {
-                None => {
-                    // Our Font cannot represent the current character. Leave a little space.
-                    current_x += 5;
-                    continue;
-                }
-                Some(r) => r,
-            };
+        match image {
+            None => self.fill_triangles(&transformed, &indices),
+            Some(image) => {
+                self.prepare_image(image);
+                let texture = image.texture.as_mut().unwrap();
+                self.fill_textured_triangles(texture, &transformed, &indices);
+            }
+        }
+    }
+
+    /// Fill a rectangle whose corners are rounded off. `radii` may be a single `u32` to round
+    /// every corner the same way, or a `CornerRadii` to set each corner individually.
+    pub fn fill_rounded_rect<R: Into<shape::CornerRadii>>(&mut self, rect: shape::Rect, radii: R) {
+        let outline = rounded_rect_fill_outline(rect, radii.into());
+        self.fill_convex(&outline, None);
+    }
+
+    /// Draw the outline of a rectangle whose corners are rounded off. See `fill_rounded_rect`.
+    pub fn draw_rounded_rect<R: Into<shape::CornerRadii>>(&mut self, rect: shape::Rect, radii: R) {
+        // Outlines cover the pixels just inside the rectangle, the same way draw_rect does.
+        let transform = self.transform;
+        let mut points: Vec<shape::Point> = shape::rounded_rect_outline(
+            rect.x() as f32,
+            rect.y() as f32,
+            (rect.width() - 1) as f32,
+            (rect.height() - 1) as f32,
+            radii.into(),
+        )
+        .into_iter()
+        .map(|(x, y)| {
+            let (x, y) = transform.apply(x + 0.5, y + 0.5);
+            shape::Point::new(x.floor() as i32, y.floor() as i32)
+        })
+        .collect();
+        points.dedup();
+        let first = points[0];
+        points.push(first);
+
+        self.prepare_to_draw();
+        self.draw_line_strip(&points);
+    }
+
+    /// Fill the rectangle with a gradient. See `Gradient` for the available styles.
+    pub fn fill_rect_gradient(&mut self, rect: shape::Rect, gradient: Gradient) {
+        let outline = rounded_rect_fill_outline(rect, shape::CornerRadii::all(0));
+        self.fill_convex(&outline, Some(gradient));
+    }
+
+    /// Fill a rounded rectangle with a gradient. See `fill_rounded_rect` and `Gradient`.
+    pub fn fill_rounded_rect_gradient<R: Into<shape::CornerRadii>>(
+        &mut self,
+        rect: shape::Rect,
+        radii: R,
+        gradient: Gradient,
+    ) {
+        let outline = rounded_rect_fill_outline(rect, radii.into());
+        self.fill_convex(&outline, Some(gradient));
+    }
+
+    /// Fill the convex shape described by `outline`, either with the draw color or with a gradient
+    /// tinted by the draw color.
+    fn fill_convex(&mut self, outline: &[(f32, f32)], gradient: Option<Gradient>) {
+        if outline.len() < 3 {
+            return;
+        }
+
+        let (mut left, mut top) = (f32::MAX, f32::MAX);
+        let (mut right, mut bottom) = (f32::MIN, f32::MIN);
+        for &(x, y) in outline {
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x);
+            bottom = bottom.max(y);
+        }
+        let (width, height) = (right - left, bottom - top);
+
+        let color = self.draw_color();
+        let color_at = |x: f32, y: f32| match gradient {
+            None => color,
+            Some(g) => g
+                .color_at(left, top, width, height, x, y)
+                .modulate(color.into())
+                .into(),
+        };
 
-            let rect = shape::Rect::new(current_x, y, font_rect.width(), font_rect.height());
-            self.canvas
-                .copy(&(font.texture), Some(*font_rect), rect)
-                .unwrap();
+        // Fan out from the center of the shape. Long edges are split up so that radial gradients
+        // have enough vertices to look round.
+        let (cx, cy) = (left + width / 2.0, top + height / 2.0);
+        let mut vertices = vec![geometry::RawVertex::new(cx, cy, color_at(cx, cy))];
+        for (i, &(x0, y0)) in outline.iter().enumerate() {
+            let (x1, y1) = outline[(i + 1) % outline.len()];
+            let steps = match gradient {
+                Some(Gradient::Radial(..)) => ((x1 - x0).hypot(y1 - y0) / 16.0).ceil().max(1.0),
+                _ => 1.0,
+            } as usize;
+            for step in 0..steps {
+                let t = step as f32 / steps as f32;
+                let (x, y) = (x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+                vertices.push(geometry::RawVertex::new(x, y, color_at(x, y)));
+            }
+        }
+        let mut indices = vec![];
+        for i in 1..vertices.len() {
+            let next = if i + 1 == vertices.len() { 1 } else { i + 1 };
+            indices.extend_from_slice(&[0, i as i32, next as i32]);
+        }
 
-            current_x += font_rect.width() as i32;
+        // The gradient is worked out before the transform, so that it turns along with the shape.
+        for vertex in &mut vertices {
+            let (x, y) = self.transform.apply(vertex.position.x, vertex.position.y);
+            vertex.position = geometry::FPoint { x, y };
         }
 
-        shape::Rect::new(x, y, (current_x - x) as u32, font.get_height() as u32)
+        self.fill_triangles(&vertices, &indices);
     }
 
-    /// Clear the screen to black. Does not affect the current rendering color.
-    pub fn clear(&mut self) {
-        self.canvas.set_draw_color(pixels::Color::RGB(0, 0, 0));
-        self.canvas.clear();
+    /// Display the image with its top-left corner at (x, y)
+    pub fn draw_image(&mut self, image: &mut Image, x: i32, y: i32) {
+        self.draw_image_ex(image, x, y, &DrawOptions::default());
     }
 
-    /// Clear the screen to the color you specify.
-    pub fn clear_to_color(&mut self, r: u8, g: u8, b: u8) {
-        self.canvas.set_draw_color(pixels::Color::RGB(r, g, b));
-        self.canvas.clear();
+    /// Display the image at (x, y), scaled, rotated, flipped and cropped according to `options`.
+    /// See `DrawOptions` for the details.
+    pub fn draw_image_ex(&mut self, image: &mut Image, x: i32, y: i32, options: &DrawOptions) {
+        self.prepare_image(image);
+        let (width, height) = (image.width, image.height);
+        self.draw_texture(
+            image.texture.as_mut().unwrap(),
+            width,
+            height,
+            x,
+            y,
+            options,
+        );
     }
-}
 
-/**
- * Image represents a texture that can be drawn on the screen.
- *
- * Images are immutable, in the sense that they have no methods to modify their contents.
- */
-pub struct Image {
-    texture: render::Texture,
-    width: u32,
-    height: u32,
-}
+    /// Make sure the texture of the Image is up to date, so that it can be drawn.
+    fn prepare_image(&mut self, image: &mut Image) {
+        if image.dirty.is_some() && image.texture.is_some() {
+            // The batch may still have to draw the old pixels, or even the old texture.
+            self.flush_batch();
+        }
+        self.upload_image(image).unwrap();
+    }
 
-impl Image {
-    pub fn get_width(&self) -> u32 {
-        self.width
+    /// Display the contents of the Canvas with its top-left corner at (x, y).
+    pub fn draw_canvas(&mut self, canvas: &mut Canvas, x: i32, y: i32) {
+        self.draw_canvas_ex(canvas, x, y, &DrawOptions::default());
     }
-    pub fn get_height(&self) -> u32 {
-        self.height
+
+    /// Display the contents of the Canvas at (x, y) according to `options`, like `draw_image_ex`.
+    pub fn draw_canvas_ex(&mut self, canvas: &mut Canvas, x: i32, y: i32, options: &DrawOptions) {
+        let (width, height) = (canvas.width, canvas.height);
+        self.draw_texture(&mut canvas.texture, width, height, x, y, options);
     }
-}
 
-/**
- * Font is a way to render text, loaded from a specially formatted image.
- *
- * Note that Font is not loaded from a TrueType file, but instead, from a specially formatted
- * image. Loading from an image is a little faster and a little simpler and a little more portable,
- * but has a couple disadvantages. For one, the font size is fixed by the file. To have two
- * different font sizes, you have to create two different Fonts from two different files. Another
- * disadvantage is that these special images are less widely available.
- *
- * This link describes how ImageFonts work: https://love2d.org/wiki/Tutorial:Fonts_and_Text
- */
-pub struct Font {
-    texture: render::Texture,
-    chars: HashMap<char, shape::Rect>,
-    height: u32,
-}
+    /// Copy the texture to the screen. This is the shared implementation of `draw_image_ex` and
+    /// friends.
+    fn draw_texture(
+        &mut self,
+        texture: &mut render::Texture,
+        texture_width: u32,
+        texture_height: u32,
+        x: i32,
+        y: i32,
+        options: &DrawOptions,
+    ) {
+        let source = options
+            .source
+            .unwrap_or_else(|| shape::Rect::new(0, 0, texture_width, texture_height));
+        let (width, height) = options
+            .size
+            .unwrap_or_else(|| (source.width(), source.height()));
+        let scaled_width = (width as f32 * options.scale_x).round().max(0.0) as u32;
+        let scaled_height = (height as f32 * options.scale_y).round().max(0.0) as u32;
+        if scaled_width == 0 || scaled_height == 0 {
+            return;
+        }
 
-impl Font {
-    /// Determine whether "ch" exists in this Font.
-    pub fn is_printable(&self, ch: char) -> bool {
-        self.chars.contains_key(&ch)
+        // The origin is given in unscaled pixels, but SDL wants it relative to the destination.
+        let origin = shape::Point::new(
+            (options.origin.0 as f32 * scaled_width as f32 / width as f32).round() as i32,
+            (options.origin.1 as f32 * scaled_height as f32 / height as f32).round() as i32,
+        );
+        let dest = shape::Rect::new(x - origin.x(), y - origin.y(), scaled_width, scaled_height);
+
+        let copy = TextureCopy {
+            source,
+            dest,
+            rotation: options.rotation,
+            center: origin,
+            flip_horizontal: options.flip_horizontal,
+            flip_vertical: options.flip_vertical,
+        };
+        let (state, color) = (self.batch_state(), self.draw_color());
+        match self.batch {
+            Some(ref mut batch) => {
+                let vertices = copy.vertices(&self.transform, texture, color);
+                batch.push(state, Some(texture), &vertices, &batch::QUAD_INDICES);
+            }
+            None => {
+                self.prepare_texture(texture);
+                copy.draw(&mut self.canvas, &self.transform, texture, color)
+                    .unwrap();
+            }
+        }
     }
 
-    /// Return the number of printable characters that the Font contains.
-    pub fn len(&self) -> usize {
-        self.chars.len()
+    /// Display one sprite from the sheet with its top-left corner at (x, y). `key` is either the
+    /// index or the name of the sprite.
+    ///
+    /// NOTE: This function panics if the sheet has no sprite for `key`.
+    pub fn draw_sprite<K: SpriteKey>(&mut self, sheet: &mut SpriteSheet, key: K, x: i32, y: i32) {
+        self.draw_sprite_ex(sheet, key, x, y, &DrawOptions::default());
     }
 
-    /// Return the height of the Font. This is constant for every possible character, while the
-    /// individual character widths vary. Note that certain characters (such a single quote `'`)
-    /// might not actually take up all of `height`. However, no character may exceed this limit.
-    pub fn get_height(&self) -> u32 {
-        self.height
+    /// Display one sprite from the sheet at (x, y) according to `options`, like `draw_image_ex`.
+    /// `options.source` is ignored, and `options.origin` is measured from the top-left corner of
+    /// the untrimmed sprite.
+    ///
+    /// NOTE: This function panics if the sheet has no sprite for `key`.
+    pub fn draw_sprite_ex<K: SpriteKey>(
+        &mut self,
+        sheet: &mut SpriteSheet,
+        key: K,
+        x: i32,
+        y: i32,
+        options: &DrawOptions,
+    ) {
+        let sprite = *sheet.get(key).expect("no such sprite in sprite sheet");
+        let offset = sprite.flipped_offset(options.flip_horizontal, options.flip_vertical);
+        let options = DrawOptions {
+            source: Some(sprite.rect),
+            origin: (options.origin.0 - offset.0, options.origin.1 - offset.1),
+            ..*options
+        };
+        self.draw_image_ex(sheet.image_mut(), x, y, &options);
     }
 
-    /// Return the portion of the Font's texture that is used to draw the `char` you provide. If
-    /// the character can't be drawn by this Font, return None.
-    fn get_rect(&self, ch: char) -> Option<&shape::Rect> {
-        self.chars.get(&ch)
+    /// Display the current frame of the Animation with its top-left corner at (x, y). The
+    /// Animation's sprite indices refer to `sheet`.
+    pub fn draw_animation(
+        &mut self,
+        sheet: &mut SpriteSheet,
+        animation: &Animation,
+        x: i32,
+        y: i32,
+    ) {
+        self.draw_sprite(sheet, animation.current_sprite(), x, y);
     }
-}
 
-/// This is the default font.
-const DEFAULT_FONT_BYTES: &'static [u8] = include_bytes!("default_font.png");
-const DEFAULT_FONT_STR: &'static str =
-    " abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789.,!?-+/():;%&`'*#=[]\"";
+    /// Display the current frame of the Animation at (x, y) according to `options`, like
+    /// `draw_sprite_ex`.
+    pub fn draw_animation_ex(
+        &mut self,
+        sheet: &mut SpriteSheet,
+        animation: &Animation,
+        x: i32,
+        y: i32,
+        options: &DrawOptions,
+    ) {
+        self.draw_sprite_ex(sheet, animation.current_sprite(), x, y, options);
+    }
 
-/// Resource Loading Methods
-/// ========================
-impl Window {
-    /// Load the image at the path you specify.
-    pub fn load_image_from_file(&self, filename: &Path) -> Result<Image, String> {
-        let mut texture = try!(self.canvas.texture_creator().load_texture(&filename));
-        texture.set_blend_mode(render::BlendMode::Blend);
-        Ok(Image {
-            width: texture.query().width,
-            height: texture.query().height,
-            texture: texture,
-        })
+    /// Write the text to the screen at (x, y) using the currently set font on the Window. Every
+    /// `\n` starts a new line. Return a Rectangle describing the area of the screen that was
+    /// modified.
+    pub fn print(&mut self, text: &str, x: i32, y: i32) -> shape::Rect {
+        self.print_ex(text, x, y, &PrintOptions::default())
     }
 
-    /// Load an image from a slice of bytes. This function is particularly powerful when
-    /// used in conjunction with the `include_bytes` macro that embeds data in the compiled
-    /// executable. In this way, you can pack all of your game data into your executable.
-    pub fn load_image(&self, data: &[u8]) -> Result<Image, String> {
-        let rwops = try!(rwops::RWops::from_bytes(data));
-        let surf: surface::Surface = try!(rwops.load());
-        let mut texture = match self
-            .canvas
-            .texture_creator()
-            .create_texture_from_surface(&surf)
-        {
-            Ok(t) => t,
-            Err(e) => return Err(e.to_string()),
+    /// Write the text to the screen at (x, y) like `print`, scaled and with a drop shadow or an
+    /// outline according to `options`. See `PrintOptions`. Return a Rectangle describing the area
+    /// of the screen that was modified, shadow and outline included. Outlines are drawn by
+    /// printing the text again at every offset around it, so thick ones cost many passes.
+    ///
+    /// NOTE: This function panics if `options.scale` isn't a positive number.
+    pub fn print_ex(&mut self, text: &str, x: i32, y: i32, options: &PrintOptions) -> shape::Rect {
+        assert!(options.scale > 0.0, "text scale must be positive");
+        let mut font = self.font.take().unwrap();
+        let (placed, width, height) = font.place_lines(text);
+        let glyphs = font.glyph_rects(&placed);
+
+        // Everything is drawn in the font's own pixels, and scaled up around (x, y).
+        self.push_transform();
+        self.translate(x as f32, y as f32);
+        self.scale(options.scale, options.scale);
+
+        if let Some(color) = options.shadow {
+            let color = self.blend_color(color.into());
+            self.draw_glyphs(&mut font, &glyphs, options.shadow_offset, color);
+        }
+        if let Some(color) = options.outline {
+            let color = self.blend_color(color.into());
+            for offset in options.outline_offsets() {
+                self.draw_glyphs(&mut font, &glyphs, offset, color);
+            }
+        }
+        let color = self.draw_color();
+        self.draw_glyphs(&mut font, &glyphs, (0, 0), color);
+
+        self.pop_transform();
+        self.font = Some(font);
+        options.area(x, y, width, height)
+    }
+
+    /// Write the text inside of `rect`, wrapping it onto as many lines as needed and lining each
+    /// line up according to `align`. Text that doesn't fit is cut off with "...". Return the
+    /// Rectangle that the text covers. See `print_rect_ex` for more options.
+    pub fn print_rect(&mut self, text: &str, rect: shape::Rect, align: Align) -> shape::Rect {
+        let options = TextOptions {
+            align,
+            ..Default::default()
         };
-        texture.set_blend_mode(render::BlendMode::Blend);
-        Ok(Image {
-            width: texture.query().width,
-            height: texture.query().height,
-            texture: texture,
-        })
+        self.print_rect_ex(text, rect, &options)
     }
 
-    // TODO: Split this out so it can be tested.
+    /// Write the text inside of `rect` according to `options`, like `print_rect`. Lines break at
+    /// every `\n` as well as wherever the text gets too wide. At least one line is always written,
+    /// even if `rect` isn't tall enough for it.
+    pub fn print_rect_ex(
+        &mut self,
+        text: &str,
+        rect: shape::Rect,
+        options: &TextOptions,
+    ) -> shape::Rect {
+        let (placed, covered) = self.layout_text(text, rect, options);
+        let color = self.draw_color();
+        self.print_chars(&placed, color);
+        covered
+    }
 
-    /// Parse a font from the Surface, using the string as a guideline.
-    fn parse_image_font(&self, surf: surface::Surface, string: String) -> Result<Font, String> {
-        if util::string_has_duplicate_chars(string.clone()) {
-            return Err("image font string has duplicate characters".to_string());
-        }
-
-        let surf = surf;
-        let mut chars: HashMap<char, shape::Rect> = HashMap::new();
-
-        let surf_width = surf.width();
-        let surf_height = surf.height();
-        let mut current_rect: Option<shape::Rect> = None;
-
-        surf.with_lock(|pixels| {
-            // `pixels` is an array of [u8; width * height]
-            let border_color = pixels[0];
-
-            // Move through the surface and divide it into rectangles according to the color of the
-            // topmost pixel.
-            for i in 0..(surf_width as usize) {
-                if pixels[i] == border_color {
-                    match current_rect {
-                        Some(mut rect) => {
-                            let c = match string.chars().nth(chars.len()) {
-                                Some(c) => c,
-                                None => {
-                                    // Out of characters to add to the hashmap, so just return with
-                                    // what have parsed so far.
-                                    return;
-                                }
-                            };
-                            rect = shape::Rect::new(
-                                rect.x(),
-                                rect.y(),
-                                ((i as i32) - rect.x()) as u32,
-                                rect.height(),
-                            );
-                            chars.insert(c, rect.clone());
-                            current_rect = None;
+    /// Return the width and height of the text if it were printed with `print`, without printing
+    /// it. Every `\n` starts a new line.
+    pub fn measure_text(&self, text: &str) -> (u32, u32) {
+        self.current_font().measure(text)
+    }
+
+    /// Return the width and height of the text if it were printed with `print_rect_ex` into a
+    /// rectangle `width` pixels wide and tall enough for all of it, without printing it.
+    pub fn measure_text_wrapped(
+        &self,
+        text: &str,
+        width: u32,
+        options: &TextOptions,
+    ) -> (u32, u32) {
+        let rect = shape::Rect::new(0, 0, width, i32::MAX as u32);
+        let options = TextOptions {
+            vertical_align: VerticalAlign::Top,
+            ..*options
+        };
+        let (_, covered) = self.layout_text(text, rect, &options);
+        (covered.width(), covered.height())
+    }
+
+    /// Write markup text to the screen at (x, y), with inline colors, fonts and images. Markup
+    /// is plain text with these tags in it:
+    ///
+    /// - `[color=#ff0]...[/color]` prints the text inside in another color. Anything that
+    ///   `Color` can parse works, including names like `orange`.
+    /// - `[font=name]...[/font]` prints the text inside in a font from `style`.
+    /// - `[img=name]` places an image from `style` in the text, like a character.
+    /// - `[[` prints a single `[`.
+    ///
+    /// Tags of the same kind nest. Everything on a line stands on the same baseline, and every
+    /// `\n` starts a new line. Text colors replace the Window's color. Images are drawn in their
+    /// own colors, faded by the alpha of the Window's color.
+    ///
+    /// ```rust,no_run
+    /// # let mut app = simple::Window::new("hello world", 640, 480);
+    /// # let coin = app.load_image(&[]).unwrap();
+    /// let mut style = simple::MarkupStyle::new();
+    /// style.add_image("coin", coin);
+    /// app.print_markup("[img=coin] x [color=#ff0]100[/color]", 10, 10, &mut style)
+    ///     .unwrap();
+    /// ```
+    ///
+    /// Return the Rectangle that the text covers, or an error if the markup is malformed or names
+    /// something that `style` doesn't have.
+    pub fn print_markup(
+        &mut self,
+        markup: &str,
+        x: i32,
+        y: i32,
+        style: &mut MarkupStyle,
+    ) -> Result<shape::Rect, String> {
+        let pieces = markup::parse(markup)?;
+        let (placed, covered) = self.layout_markup(&pieces, style, x, y)?;
+        for piece in placed {
+            match piece {
+                PlacedPiece::Text { font, color, chars } => {
+                    let color = self.blend_color(color.map_or(self.foreground_color, |c| c.into()));
+                    match font {
+                        Some(name) => {
+                            let font = style.fonts.get_mut(name).unwrap();
+                            self.print_glyphs(font, &chars, color);
                         }
-                        None => (),
+                        None => self.print_chars(&chars, color),
                     }
-                } else {
-                    match current_rect {
-                        Some(_) => (),
-                        None => {
-                            current_rect = Some(shape::Rect::new(i as i32, 0, 1, surf_height));
-                        }
+                }
+                PlacedPiece::Image { name, x, y } => {
+                    let color = self.foreground_color;
+                    self.foreground_color = pixels::Color::RGBA(255, 255, 255, color.a);
+                    self.draw_image(style.images.get_mut(name).unwrap(), x, y);
+                    self.foreground_color = color;
+                }
+            }
+        }
+        Ok(covered)
+    }
+
+    /// Return the width and height of the markup if it were printed with `print_markup`, without
+    /// printing it.
+    pub fn measure_markup(&self, markup: &str, style: &MarkupStyle) -> Result<(u32, u32), String> {
+        let pieces = markup::parse(markup)?;
+        let (_, covered) = self.layout_markup(&pieces, style, 0, 0)?;
+        Ok((covered.width(), covered.height()))
+    }
+
+    /// Return the Font that `print` uses.
+    fn current_font(&self) -> &Font {
+        match self.font {
+            Some(ref f) => f,
+
+            // FIXME: shouldn't be possible to have no font, and the `font` field on Window should
+            // be updated to reflect this.
+            None => panic!("no font set on window"),
+        }
+    }
+
+    /// Work out where every piece of markup goes when it is printed at (x, y). Return the pieces
+    /// with their positions, and the Rectangle that they cover.
+    fn layout_markup<'a>(
+        &self,
+        pieces: &'a [Piece],
+        style: &MarkupStyle,
+        x: i32,
+        y: i32,
+    ) -> Result<(Vec<PlacedPiece<'a>>, shape::Rect), String> {
+        let mut placed = vec![];
+        let (mut top, mut right) = (y, x);
+        for line in pieces.split(|piece| *piece == Piece::Newline) {
+            // The line is tall enough for the tallest font or image above the baseline, and the
+            // deepest font below it. Empty lines are as tall as the Window's font.
+            let (mut baseline, mut descent) = (0, 0);
+            if line.is_empty() {
+                let font = self.current_font();
+                baseline = font.baseline as i32;
+                descent = font.height as i32 - baseline;
+            }
+            for piece in line {
+                match *piece {
+                    Piece::Text { ref font, .. } => {
+                        let font = self.markup_font(style, font)?;
+                        baseline = baseline.max(font.baseline as i32);
+                        descent = descent.max(font.height as i32 - font.baseline as i32);
                     }
+                    Piece::Image(ref name) => {
+                        baseline = baseline.max(style.image(name)?.height as i32);
+                    }
+                    Piece::Newline => (),
                 }
             }
-        });
 
-        let mut texture = match self
-            .canvas
-            .texture_creator()
-            .create_texture_from_surface(&surf)
-        {
-            Ok(t) => t,
-            Err(e) => return Err(e.to_string()),
+            let mut pen = x;
+            for piece in line {
+                match *piece {
+                    Piece::Text {
+                        ref text,
+                        color,
+                        ref font,
+                    } => {
+                        let markup_font = self.markup_font(style, font)?;
+                        let line_top = top + baseline - markup_font.baseline as i32;
+                        let (chars, end) = markup_font.place(text, pen, line_top);
+                        pen = end;
+                        placed.push(PlacedPiece::Text {
+                            font: font.as_ref().map(|f| f.as_str()),
+                            color,
+                            chars,
+                        });
+                    }
+                    Piece::Image(ref name) => {
+                        let image = style.image(name)?;
+                        placed.push(PlacedPiece::Image {
+                            name,
+                            x: pen,
+                            y: top + baseline - image.height as i32,
+                        });
+                        pen += image.width as i32;
+                    }
+                    Piece::Newline => (),
+                }
+            }
+            right = right.max(pen);
+            top += baseline + descent;
+        }
+        let covered = shape::Rect::new(x, y, (right - x) as u32, (top - y) as u32);
+        Ok((placed, covered))
+    }
+
+    /// Return the font that markup text is printed in: the one named in `style`, or the Window's.
+    fn markup_font<'a>(
+        &'a self,
+        style: &'a MarkupStyle,
+        name: &Option<String>,
+    ) -> Result<&'a Font, String> {
+        match *name {
+            Some(ref name) => style
+                .fonts
+                .get(name)
+                .ok_or_else(|| format!("markup style has no font named {:?}", name)),
+            None => Ok(self.current_font()),
+        }
+    }
+
+    /// Work out where every character of the text goes when it is printed inside of `rect`.
+    /// Return the characters with their positions, and the Rectangle that they cover.
+    fn layout_text(
+        &self,
+        text: &str,
+        rect: shape::Rect,
+        options: &TextOptions,
+    ) -> (Vec<(char, i32, i32)>, shape::Rect) {
+        let font = self.current_font();
+        let max_width = rect.width() as i32;
+        let mut lines = text::wrap(text, Some(max_width), font);
+
+        let line_height = font.get_height() as i32;
+        let step = line_height + options.line_spacing;
+        let mut ellipsized = None;
+        if step > 0 {
+            let fit = ((rect.height() as i32 + options.line_spacing) / step).max(1) as usize;
+            if lines.len() > fit {
+                lines.truncate(fit);
+                if options.ellipsis {
+                    let line = text::ellipsize(text, lines[fit - 1], max_width, font);
+                    ellipsized = Some(format!("{}...", &text[line.start..line.end]));
+                    lines[fit - 1] = line;
+                }
+            }
+        }
+
+        let height = lines.len() as i32 * step - options.line_spacing;
+        let top = match options.vertical_align {
+            VerticalAlign::Top => rect.y(),
+            VerticalAlign::Middle => rect.y() + (rect.height() as i32 - height) / 2,
+            VerticalAlign::Bottom => rect.bottom() - height,
         };
-        texture.set_blend_mode(render::BlendMode::Blend);
-        Ok(Font {
-            height: texture.query().height,
-            texture: texture,
-            chars: chars,
-        })
+
+        let mut placed = vec![];
+        let (mut left, mut right) = (rect.right(), rect.x());
+        for (i, &line) in lines.iter().enumerate() {
+            let (shown, ends_paragraph) = match ellipsized {
+                Some(ref shortened) if i == lines.len() - 1 => (&shortened[..], true),
+                _ => (&text[line.start..line.end], line.ends_paragraph),
+            };
+            let shown = font.display_line(shown);
+            let line = Line {
+                start: 0,
+                end: shown.len(),
+                width: text::width(&shown, font),
+                ends_paragraph,
+            };
+            let chars = text::place_line(&shown, line, rect.x(), max_width, options.align, font);
+            if let (Some(&(_, first)), Some(&(last_ch, last))) = (chars.first(), chars.last()) {
+                left = left.min(first);
+                right = right.max(last + font.char_width(last_ch));
+            }
+            let y = top + i as i32 * step;
+            placed.extend(chars.into_iter().map(|(ch, x)| (ch, x, y)));
+        }
+
+        let width = (right - left).max(0) as u32;
+        let covered = shape::Rect::new(left.min(right), top, width, height.max(0) as u32);
+        (placed, covered)
     }
 
-    /// Load a Font from the hard drive. See the documentation on `Font` for details.
-    pub fn load_font_from_file(&self, filename: &Path, string: String) -> Result<Font, String> {
-        let surf: surface::Surface = try!(LoadSurface::from_file(filename));
-        self.parse_image_font(surf, string)
+    /// Draw each character in the Window's Font at its position, with its top-left corner at the
+    /// top of the line.
+    fn print_chars(&mut self, placed: &[(char, i32, i32)], color: pixels::Color) {
+        let mut font = self.font.take().unwrap();
+        self.print_glyphs(&mut font, placed, color);
+        self.font = Some(font);
     }
 
-    /// Load a Font from a slice of bytes. See the documentation on `Font` for details. This
-    /// function is particularly powerful when used in conjunction with the `include_bytes` macro
-    /// that embeds data in the compiled executable.
+    /// Draw each character in `font` at its position, like `print_chars`.
+    fn print_glyphs(&mut self, font: &mut Font, placed: &[(char, i32, i32)], color: pixels::Color) {
+        let glyphs = font.glyph_rects(placed);
+        self.draw_glyphs(font, &glyphs, (0, 0), color);
+    }
+
+    /// Copy each `(source, dest)` pair of `glyphs` from the Font's image to the screen, with
+    /// `offset` added to every `dest`.
+    fn draw_glyphs(
+        &mut self,
+        font: &mut Font,
+        glyphs: &[(shape::Rect, shape::Rect)],
+        offset: (i32, i32),
+        color: pixels::Color,
+    ) {
+        self.prepare_to_draw();
+        let state = self.batch_state();
+        let old_textures = font.take_old_textures();
+        if !old_textures.is_empty() {
+            self.flush_batch();
+            for texture in old_textures {
+                // The canvas is alive, since we are borrowing it, so this is safe.
+                unsafe { texture.destroy() };
+            }
+        }
+        self.prepare_image(&mut font.image);
+        let texture = font.image.texture.as_mut().unwrap();
+        util::set_texture_color(&color, texture);
+        self.apply_blend_mode(texture);
+        for &(source, dest) in glyphs {
+            let dest = shape::Rect::new(
+                dest.x() + offset.0,
+                dest.y() + offset.1,
+                dest.width(),
+                dest.height(),
+            );
+            let copy = TextureCopy::new(source, dest);
+            match self.batch {
+                Some(ref mut batch) => {
+                    let vertices = copy.vertices(&self.transform, texture, color);
+                    batch.push(state, Some(texture), &vertices, &batch::QUAD_INDICES);
+                }
+                None => copy
+                    .draw(&mut self.canvas, &self.transform, texture, color)
+                    .unwrap(),
+            }
+        }
+    }
+
+    /// Draw the Text at (x, y) in the current font, laying it out again only if the string or
+    /// the font has changed since it was last drawn. Return a Rectangle describing the area of
+    /// the screen that was modified. See `Text`.
+    pub fn draw_text(&mut self, text: &mut Text, x: i32, y: i32) -> shape::Rect {
+        let version = self.current_font().version;
+        if text
+            .layout
+            .as_ref()
+            .map_or(true, |l| l.font_version != version)
+        {
+            let mut font = self.font.take().unwrap();
+            text.layout = Some(font.layout(&text.string));
+            self.font = Some(font);
+        }
+        let layout = text.layout.as_ref().unwrap();
+        let rect = shape::Rect::new(x, y, layout.width, layout.height);
+
+        // Renderers that can't draw onto textures, or can't draw them premultiplied, get the text
+        // one character at a time instead.
+        let blend_mode = self.blend_mode;
+        let through_canvas = text.to_texture
+            && self.set_blend_mode(BlendMode::Premultiplied).is_ok()
+            && self.draw_text_to_canvas(text).is_ok();
+        if !through_canvas {
+            // This mode was in use already, so it can be used again.
+            self.set_blend_mode(blend_mode).ok();
+            let layout = text.layout.as_ref().unwrap();
+            let mut font = self.font.take().unwrap();
+            let color = self.draw_color();
+            self.draw_glyphs(&mut font, &layout.glyphs, (x, y), color);
+            self.font = Some(font);
+            return rect;
+        }
+
+        let canvas = text.canvas.as_mut().unwrap();
+        let options = DrawOptions {
+            source: Some(shape::Rect::new(0, 0, rect.width(), rect.height())),
+            ..Default::default()
+        };
+        self.draw_canvas_ex(canvas, x, y, &options);
+        self.set_blend_mode(blend_mode).ok();
+        rect
+    }
+
+    /// Make sure the Text's Canvas is big enough and has its current layout drawn on it. The
+    /// Window's BlendMode is left as it was.
+    fn draw_text_to_canvas(&mut self, text: &mut Text) -> Result<(), String> {
+        let layout = text.layout.as_mut().unwrap();
+        let (width, height) = (layout.width.max(1), layout.height.max(1));
+        let too_small = text
+            .canvas
+            .as_ref()
+            .is_some_and(|c| c.width < width || c.height < height);
+        if too_small {
+            // The batch may still be drawing from the old texture.
+            self.flush_batch();
+            // The canvas is alive, since we are borrowing it, so this is safe.
+            unsafe { text.canvas.take().unwrap().texture.destroy() };
+        }
+        if text.canvas.is_none() {
+            text.canvas = Some(self.create_canvas(width, height)?);
+            layout.drawn_to_canvas = false;
+        }
+        if layout.drawn_to_canvas {
+            return Ok(());
+        }
+
+        // Drawing white onto transparent black with alpha blending leaves premultiplied white,
+        // which the Window's color can then tint.
+        let canvas = text.canvas.as_mut().unwrap();
+        let blend_mode = self.blend_mode;
+        let glyphs = &layout.glyphs;
+        let drawn = self.with_target(canvas, |w| {
+            w.clear_to_transparent();
+            // Every renderer supports Alpha.
+            w.set_blend_mode(BlendMode::Alpha).ok();
+            let mut font = w.font.take().unwrap();
+            let white = pixels::Color::RGBA(255, 255, 255, 255);
+            w.draw_glyphs(&mut font, glyphs, (0, 0), white);
+            w.font = Some(font);
+        });
+        self.set_blend_mode(blend_mode).ok();
+        drawn?;
+        layout.drawn_to_canvas = true;
+        Ok(())
+    }
+
+    /// Clear the screen to black. Does not affect the current rendering color.
+    pub fn clear(&mut self) {
+        self.discard_batch();
+        self.canvas.set_draw_color(pixels::Color::RGB(0, 0, 0));
+        self.canvas.clear();
+    }
+
+    /// Clear the screen to the color you specify, which may be a Color or a tuple of channels like
+    /// for `set_color`. The alpha channel is used too, which matters when clearing a Canvas.
+    pub fn clear_to_color<C: Into<Color>>(&mut self, color: C) {
+        self.discard_batch();
+        self.canvas.set_draw_color(color.into());
+        self.canvas.clear();
+    }
+
+    /// Clear the screen to fully transparent. This is mostly useful inside of `with_target`, to
+    /// wipe a Canvas before redrawing it.
+    pub fn clear_to_transparent(&mut self) {
+        self.discard_batch();
+        self.canvas.set_draw_color(pixels::Color::RGBA(0, 0, 0, 0));
+        self.canvas.clear();
+    }
+}
+
+/// Transform Methods
+/// =================
+///
+/// The Window has a current transform that every draw call passes through on its way to the
+/// screen. This is how you build a camera: translate by the negated camera position, and every
+/// draw call can keep using world coordinates. Transforms are applied in reverse order of the
+/// calls that set them up, so the last call affects the coordinates you draw with first.
+///
+/// ```rust,no_run
+/// # let mut app = simple::Window::new("hello world", 640, 480);
+/// # let (camera_x, camera_y, zoom) = (0.0, 0.0, 1.0);
+/// while app.next_frame() {
+///     app.clear();
+///     app.push_transform();
+///     app.translate(320.0, 240.0);
+///     app.scale(zoom, zoom);
+///     app.translate(-camera_x, -camera_y);
+///     // ... draw the level in world coordinates ...
+///     app.pop_transform();
+///
+///     // ... draw the HUD in screen coordinates ...
+/// }
+/// ```
+impl Window {
+    /// Move everything drawn from now on by (x, y).
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.apply_transform(&Transform::translation(x, y));
+    }
+
+    /// Stretch everything drawn from now on by `x` horizontally and `y` vertically, away from the
+    /// current origin. Scaling both by the same amount zooms in or out.
+    pub fn scale(&mut self, x: f32, y: f32) {
+        self.apply_transform(&Transform::scaling(x, y));
+    }
+
+    /// Turn everything drawn from now on around the current origin by `angle` radians clockwise.
+    pub fn rotate(&mut self, angle: f32) {
+        self.apply_transform(&Transform::rotation(angle));
+    }
+
+    /// Apply `transform` to everything drawn from now on, before the current transform.
+    pub fn apply_transform(&mut self, transform: &Transform) {
+        self.transform = transform.then(&self.transform);
+    }
+
+    /// Return the current transform.
+    pub fn get_transform(&self) -> Transform {
+        self.transform
+    }
+
+    /// Replace the current transform.
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    /// Go back to drawing in screen coordinates. The stack saved by `push_transform` is kept.
+    pub fn reset_transform(&mut self) {
+        self.transform = Transform::identity();
+    }
+
+    /// Save the current transform, so that it can be restored by `pop_transform`.
+    pub fn push_transform(&mut self) {
+        self.transform_stack.push(self.transform);
+    }
+
+    /// Restore the transform saved by the matching `push_transform`.
+    ///
+    /// NOTE: This function panics if there was no matching call to `push_transform`.
+    pub fn pop_transform(&mut self) {
+        self.transform = self
+            .transform_stack
+            .pop()
+            .expect("pop_transform called without push_transform");
+    }
+
+    /// Convert a position on the screen, like the position of a mouse click, into the coordinates
+    /// that draw calls use under the current transform.
+    pub fn screen_to_world(&self, x: i32, y: i32) -> (f32, f32) {
+        match self.transform.inverse() {
+            Some(inverse) => inverse.apply(x as f32, y as f32),
+            // everything is being squashed flat, so nothing is under any point on the screen
+            None => (0.0, 0.0),
+        }
+    }
+
+    /// Convert a position in the coordinates that draw calls use into a position on the screen.
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (i32, i32) {
+        let (x, y) = self.transform.apply(x, y);
+        (x.floor() as i32, y.floor() as i32)
+    }
+}
+
+/// Clipping Methods
+/// ================
+impl Window {
+    /// Only draw inside of `rect` until the matching call to `pop_clip`. If a clip is already set,
+    /// drawing is restricted to the area inside both of them.
+    ///
+    /// `rect` passes through the current transform. If the transform rotates, drawing is
+    /// restricted to the smallest upright rectangle around the rotated `rect`.
+    pub fn push_clip(&mut self, rect: shape::Rect) {
+        let rect = self.transform.apply_rect(rect);
+        let clip = match self.clip_stack.last() {
+            Some(&Some(current)) => current.intersection(rect),
+            Some(&None) => None,
+            None => Some(rect),
+        };
+        self.clip_stack.push(clip);
+        self.apply_clip();
+    }
+
+    /// Remove the clip set by the matching call to `push_clip`.
+    ///
+    /// NOTE: This function panics if there was no matching call to `push_clip`.
+    pub fn pop_clip(&mut self) {
+        self.clip_stack
+            .pop()
+            .expect("pop_clip called without push_clip");
+        self.apply_clip();
+    }
+
+    /// Bring SDL's clip rectangle up to date with `self.clip_stack`. While batching, every draw
+    /// call remembers its own clip rectangle instead.
+    fn apply_clip(&mut self) {
+        if self.batch.is_none() {
+            let clip = self.current_clip();
+            self.canvas.set_clip_rect(clip);
+        }
+    }
+
+    /// Return the clip rectangle to give SDL for the top of `self.clip_stack`.
+    fn current_clip(&self) -> Option<shape::Rect> {
+        match self.clip_stack.last() {
+            Some(&Some(rect)) => Some(rect),
+            // SDL can't clip to an empty rectangle, so clip to a pixel that can't be seen instead.
+            Some(&None) => Some(shape::Rect::new(-10_000, -10_000, 1, 1)),
+            None => None,
+        }
+    }
+}
+
+/// Batching Methods
+/// ================
+///
+/// Normally every draw call goes straight to SDL. While batching, draw calls are collected
+/// instead, and drawn all at once when `next_frame` is called, sorted by layer (see `set_layer`).
+/// The `BatchMode` passed to `begin_batch` decides whether draw calls may also be reordered
+/// within a layer to draw faster.
+///
+/// Switching to a Canvas with `with_target` draws everything collected so far first.
+///
+/// ```rust,no_run
+/// # let mut app = simple::Window::new("hello world", 640, 480);
+/// app.begin_batch(simple::BatchMode::KeepOrder);
+/// while app.next_frame() {
+///     app.clear();
+///
+///     app.set_layer(10);
+///     app.print("score: 100", 10, 10); // drawn on top, even though it comes first
+///
+///     app.set_layer(0);
+///     app.fill_rect(simple::Rect::new(0, 0, 640, 480));
+/// }
+/// ```
+impl Window {
+    /// Start batching draw calls, ordering them within each layer according to `mode`. Batching
+    /// stays on across frames until `end_batch`. Calling `begin_batch` while already batching
+    /// draws everything collected so far and switches to the new mode.
+    pub fn begin_batch(&mut self, mode: BatchMode) {
+        self.flush_batch();
+        self.batch = Some(Batch::new(mode));
+    }
+
+    /// Draw everything collected since `begin_batch` and go back to drawing straight away.
+    pub fn end_batch(&mut self) {
+        self.flush_batch();
+        self.batch = None;
+    }
+
+    /// Return true between `begin_batch` and `end_batch`.
+    pub fn is_batching(&self) -> bool {
+        self.batch.is_some()
+    }
+
+    /// Put batched draw calls from now on onto `layer`. Lower layers are drawn first. The default
+    /// layer is 0. Layers only matter while batching.
+    pub fn set_layer(&mut self, layer: i32) {
+        self.layer = layer;
+    }
+
+    /// Return the layer set by `set_layer`.
+    pub fn get_layer(&self) -> i32 {
+        self.layer
+    }
+
+    /// Return the state that batched draw calls are made with.
+    fn batch_state(&self) -> batch::State {
+        batch::State {
+            layer: self.layer,
+            blend_mode: self.blend_mode,
+            clip: self.current_clip(),
+        }
+    }
+
+    /// Draw everything in the batch, if there is one.
+    fn flush_batch(&mut self) {
+        if let Some(ref mut batch) = self.batch {
+            if !batch.is_empty() {
+                batch.flush(&mut self.canvas).unwrap();
+                blend::set_draw_blend_mode(&mut self.canvas, self.blend_mode).unwrap();
+                let clip = self.current_clip();
+                self.canvas.set_clip_rect(clip);
+            }
+        }
+    }
+
+    /// Throw away everything in the batch, because it is about to be cleared anyway.
+    fn discard_batch(&mut self) {
+        if let Some(ref mut batch) = self.batch {
+            batch.clear();
+        }
+    }
+}
+
+/// Render Target Methods
+/// =====================
+impl Window {
+    /// Create a new, fully transparent Canvas to draw onto. See `Canvas` for details.
+    pub fn create_canvas(&mut self, width: u32, height: u32) -> Result<Canvas, String> {
+        let mut texture = self
+            .canvas
+            .texture_creator()
+            .create_texture_target(pixels::PixelFormatEnum::RGBA32, width, height)
+            .map_err(|e| e.to_string())?;
+        texture.set_blend_mode(render::BlendMode::Blend);
+        let mut canvas = Canvas {
+            texture,
+            width,
+            height,
+        };
+        if let Err(e) = self.with_target(&mut canvas, |w| w.clear_to_transparent()) {
+            // Nothing has drawn from the texture, so this is safe.
+            unsafe { canvas.texture.destroy() };
+            return Err(e);
+        }
+        Ok(canvas)
+    }
+
+    /// Run `draw` with every draw call redirected onto the Canvas instead of the screen. Calls to
+    /// `with_target` may be nested.
+    ///
+    /// ```rust,no_run
+    /// # let mut app = simple::Window::new("hello world", 640, 480);
+    /// let mut minimap = app.create_canvas(128, 128).unwrap();
+    /// app.with_target(&mut minimap, |w| {
+    ///     w.clear();
+    ///     w.set_color((0, 255, 0, 255));
+    ///     w.fill_rect(simple::Rect::new(10, 10, 4, 4));
+    /// })
+    /// .unwrap();
+    ///
+    /// while app.next_frame() {
+    ///     app.draw_canvas(&mut minimap, 500, 20);
+    /// }
+    /// ```
+    pub fn with_target<F>(&mut self, canvas: &mut Canvas, draw: F) -> Result<(), String>
+    where
+        F: FnOnce(&mut Window),
+    {
+        self.flush_batch();
+        let renderer = self.canvas.raw();
+        let previous = unsafe { sys::SDL_GetRenderTarget(renderer) };
+        if unsafe { sys::SDL_SetRenderTarget(renderer, canvas.texture.raw()) } != 0 {
+            return Err(sdl2::get_error());
+        }
+
+        // A Canvas has its own coordinates, so the transform and clips don't apply while drawing
+        // onto it. Batching is off too, so that everything is drawn before switching back.
+        let transform = std::mem::take(&mut self.transform);
+        let transform_stack = std::mem::take(&mut self.transform_stack);
+        let clip_stack = std::mem::take(&mut self.clip_stack);
+        let batch = self.batch.take();
+        draw(self);
+        self.flush_batch();
+        self.transform = transform;
+        self.transform_stack = transform_stack;
+        self.clip_stack = clip_stack;
+        self.batch = batch;
+
+        if unsafe { sys::SDL_SetRenderTarget(renderer, previous) } != 0 {
+            return Err(sdl2::get_error());
+        }
+        // SDL forgets the clip rectangle when switching between Canvases.
+        self.apply_clip();
+        Ok(())
+    }
+
+    /// Copy the contents of the Canvas into a new Image. This reads pixels back from the graphics
+    /// card, which is slow, so avoid doing it every frame.
+    pub fn canvas_to_image(&mut self, canvas: &mut Canvas) -> Result<Image, String> {
+        let mut pixels = Ok(vec![]);
+        self.with_target(canvas, |w| {
+            pixels = w.canvas.read_pixels(None, pixels::PixelFormatEnum::RGBA32);
+        })?;
+        Ok(Image::from_pixels(canvas.width, canvas.height, &pixels?))
+    }
+
+    /// Free the graphics memory used by the Canvas. A Canvas that is simply dropped keeps its
+    /// memory until the Window is closed, so call this when replacing one, for example when the
+    /// screen is resized.
+    pub fn destroy_canvas(&mut self, canvas: Canvas) {
+        // The batch may still be drawing from the texture.
+        self.flush_batch();
+        // The canvas is alive, since we are borrowing it, so this is safe.
+        unsafe { canvas.texture.destroy() };
+    }
+}
+
+/// One copy of a region of a texture onto the canvas, described the same way as for
+/// `Canvas::copy_ex`.
+struct TextureCopy {
+    source: shape::Rect,
+    dest: shape::Rect,
+    /// Radians clockwise around `center`.
+    rotation: f64,
+    /// Relative to the top-left corner of `dest`.
+    center: shape::Point,
+    flip_horizontal: bool,
+    flip_vertical: bool,
+}
+
+impl TextureCopy {
+    fn new(source: shape::Rect, dest: shape::Rect) -> Self {
+        TextureCopy {
+            source,
+            dest,
+            rotation: 0.0,
+            center: shape::Point::new(0, 0),
+            flip_horizontal: false,
+            flip_vertical: false,
+        }
+    }
+
+    /// Copy the texture onto the canvas, passing the destination through `transform`. The
+    /// texture's color mod must already be set to `color`.
+    fn draw(
+        &self,
+        canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+        transform: &Transform,
+        texture: &mut render::Texture,
+        color: pixels::Color,
+    ) -> Result<(), String> {
+        let dest = self.dest;
+        if let Some(scale) = transform.uniform_scale() {
+            // SDL can do this one by itself. Rounding both edges keeps neighbouring copies, like
+            // the tiles of a map, from overlapping or leaving gaps.
+            let (left, top) = transform.apply(dest.x() as f32, dest.y() as f32);
+            let (right, bottom) = transform.apply(dest.right() as f32, dest.bottom() as f32);
+            let (left, top) = (left.round(), top.round());
+            let (right, bottom) = (right.round(), bottom.round());
+            if right <= left || bottom <= top {
+                return Ok(());
+            }
+            let dest = shape::Rect::new(
+                left as i32,
+                top as i32,
+                (right - left) as u32,
+                (bottom - top) as u32,
+            );
+            let center = shape::Point::new(
+                (self.center.x() as f32 * scale).round() as i32,
+                (self.center.y() as f32 * scale).round() as i32,
+            );
+            return canvas.copy_ex(
+                texture,
+                Some(self.source),
+                Some(dest),
+                self.rotation.to_degrees(),
+                Some(center),
+                self.flip_horizontal,
+                self.flip_vertical,
+            );
+        }
+
+        // Otherwise the copy may end up any shape, so draw it as two textured triangles.
+        let vertices = self.vertices(transform, texture, color);
+
+        // The vertices carry the color, so make sure it isn't applied twice.
+        util::set_texture_color(&pixels::Color::RGBA(255, 255, 255, 255), texture);
+        let result =
+            geometry::render_geometry(canvas, Some(texture), &vertices, &batch::QUAD_INDICES);
+        util::set_texture_color(&color, texture);
+        result
+    }
+
+    /// Return the corners of the copy as textured vertices, in order, after `transform`.
+    fn vertices(
+        &self,
+        transform: &Transform,
+        texture: &render::Texture,
+        color: pixels::Color,
+    ) -> Vec<geometry::RawVertex> {
+        let dest = self.dest;
+        let (pivot_x, pivot_y) = (
+            (dest.x() + self.center.x()) as f32,
+            (dest.y() + self.center.y()) as f32,
+        );
+        let transform = Transform::translation(-pivot_x, -pivot_y)
+            .then(&Transform::rotation(self.rotation as f32))
+            .then(&Transform::translation(pivot_x, pivot_y))
+            .then(transform);
+
+        let query = texture.query();
+        let (texture_width, texture_height) = (query.width as f32, query.height as f32);
+        let mut u = [
+            self.source.x() as f32 / texture_width,
+            self.source.right() as f32 / texture_width,
+        ];
+        let mut v = [
+            self.source.y() as f32 / texture_height,
+            self.source.bottom() as f32 / texture_height,
+        ];
+        if self.flip_horizontal {
+            u.swap(0, 1);
+        }
+        if self.flip_vertical {
+            v.swap(0, 1);
+        }
+
+        let (left, top) = (dest.x() as f32, dest.y() as f32);
+        let (right, bottom) = (dest.right() as f32, dest.bottom() as f32);
+        let corners = [
+            (left, top, u[0], v[0]),
+            (right, top, u[1], v[0]),
+            (right, bottom, u[1], v[1]),
+            (left, bottom, u[0], v[1]),
+        ];
+        corners
+            .iter()
+            .map(|&(x, y, u, v)| {
+                let (x, y) = transform.apply(x, y);
+                geometry::RawVertex::textured(x, y, color, u, v)
+            })
+            .collect()
+    }
+}
+
+/// Return the outline used to fill `rect` with rounded corners. Fills cover the whole rectangle,
+/// unlike outlines which cover the pixels just inside it.
+fn rounded_rect_fill_outline(rect: shape::Rect, radii: shape::CornerRadii) -> Vec<(f32, f32)> {
+    shape::rounded_rect_outline(
+        rect.x() as f32,
+        rect.y() as f32,
+        rect.width() as f32,
+        rect.height() as f32,
+        radii,
+    )
+}
+
+/**
+ * ScaleMode decides how the logical screen set up by `Window::set_logical_size` is fitted into the
+ * Window.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScaleMode {
+    /// Scale by the largest whole number that fits, leaving black bars around the edges. Every
+    /// logical pixel is the same size, which keeps pixel art crisp.
+    IntegerScale,
+
+    /// Scale as large as possible while keeping the aspect ratio, leaving black bars along two of
+    /// the edges if the aspect ratio of the Window is different.
+    Fit,
+
+    /// Fill the whole Window, stretching the picture if the aspect ratio is different.
+    Stretch,
+}
+
+/// Work out where a logical screen of `logical` size ends up in a window of `window` size. Return
+/// the offset of its top-left corner and its scale in each direction. This mirrors what SDL does
+/// for its own logical size.
+fn logical_viewport(
+    window: (u32, u32),
+    logical: (u32, u32),
+    mode: ScaleMode,
+) -> (f32, f32, f32, f32) {
+    if logical.0 == 0 || logical.1 == 0 {
+        return (0.0, 0.0, 1.0, 1.0);
+    }
+    let scale_x = window.0 as f32 / logical.0 as f32;
+    let scale_y = window.1 as f32 / logical.1 as f32;
+    let scale = match mode {
+        ScaleMode::Stretch => return (0.0, 0.0, scale_x, scale_y),
+        ScaleMode::Fit => scale_x.min(scale_y),
+        ScaleMode::IntegerScale => scale_x.min(scale_y).floor().max(1.0),
+    };
+    let width = (logical.0 as f32 * scale).floor();
+    let height = (logical.1 as f32 * scale).floor();
+    (
+        ((window.0 as f32 - width) / 2.0).floor(),
+        ((window.1 as f32 - height) / 2.0).floor(),
+        scale,
+        scale,
+    )
+}
+
+/**
+ * DrawOptions controls how `Window::draw_image_ex` places an image on the screen.
+ *
+ * Start from `DrawOptions::default()`, which draws the whole image at its native size, and
+ * override the fields you need:
+ *
+ * ```rust,no_run
+ * # let mut app = simple::Window::new("hello world", 640, 480);
+ * # let mut image = app.load_image_from_file(std::path::Path::new("player.png")).unwrap();
+ * // Draw the image twice as large, spinning around its center.
+ * let options = simple::DrawOptions {
+ *     scale_x: 2.0,
+ *     scale_y: 2.0,
+ *     rotation: 0.5,
+ *     origin: (image.get_width() as i32 / 2, image.get_height() as i32 / 2),
+ *     ..Default::default()
+ * };
+ * app.draw_image_ex(&mut image, 320, 240, &options);
+ * ```
+ */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DrawOptions {
+    /// The part of the image to draw. `None` draws the whole image.
+    pub source: Option<shape::Rect>,
+
+    /// The size to draw the image at, before scaling. `None` uses the size of `source`.
+    pub size: Option<(u32, u32)>,
+
+    /// Horizontal scaling factor, applied on top of `size`.
+    pub scale_x: f32,
+
+    /// Vertical scaling factor, applied on top of `size`.
+    pub scale_y: f32,
+
+    /// Clockwise rotation around `origin`, in radians.
+    pub rotation: f64,
+
+    /// The point of the image, in unscaled pixels from its top-left corner, that is placed at the
+    /// (x, y) passed to the draw call. Rotation also happens around this point.
+    pub origin: (i32, i32),
+
+    /// Mirror the image left-to-right.
+    pub flip_horizontal: bool,
+
+    /// Mirror the image top-to-bottom.
+    pub flip_vertical: bool,
+}
+
+impl Default for DrawOptions {
+    fn default() -> Self {
+        DrawOptions {
+            source: None,
+            size: None,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            rotation: 0.0,
+            origin: (0, 0),
+            flip_horizontal: false,
+            flip_vertical: false,
+        }
+    }
+}
+
+/**
+ * Image represents a texture that can be drawn on the screen.
+ *
+ * Images keep a copy of their pixels in memory, so they can be read and edited at any time. Edits
+ * are sent to the graphics card the next time the Image is drawn. Images that are edited after
+ * they have been drawn switch to a streaming texture, which is faster to update.
+ */
+pub struct Image {
+    texture: Option<render::Texture>,
+    streaming: bool,
+    width: u32,
+    height: u32,
+
+    // RGBA bytes, row by row
+    pixels: Vec<u8>,
+    // the area of `pixels` that has changed since the texture was last updated
+    dirty: Option<shape::Rect>,
+}
+
+impl Image {
+    /// Create an Image from `width * height` pixels, stored as RGBA bytes row by row. The Image can
+    /// be drawn on any Window.
+    ///
+    /// NOTE: This function panics if `pixels` is not exactly `width * height * 4` bytes long.
+    pub fn from_pixels(width: u32, height: u32, pixels: &[u8]) -> Image {
+        assert!(
+            util::rgba_len(width, height) == Some(pixels.len()),
+            "pixel data doesn't match the image size"
+        );
+        Image {
+            texture: None,
+            streaming: false,
+            width,
+            height,
+            pixels: pixels.to_vec(),
+            dirty: None,
+        }
+    }
+
+    /// Create a fully transparent Image.
+    ///
+    /// NOTE: This function panics if the Image wouldn't fit in memory.
+    pub fn new(width: u32, height: u32) -> Image {
+        let len = util::rgba_len(width, height).expect("image is too big");
+        Image::from_pixels(width, height, &vec![0; len])
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    /// Return the color of the pixel at (x, y) as `(red, green, blue, alpha)`.
+    ///
+    /// NOTE: This function panics if (x, y) is outside of the Image.
+    pub fn get_pixel(&self, x: u32, y: u32) -> (u8, u8, u8, u8) {
+        let i = self.pixel_offset(x, y);
+        let p = &self.pixels[i..i + 4];
+        (p[0], p[1], p[2], p[3])
+    }
+
+    /// Change the color of the pixel at (x, y).
+    ///
+    /// NOTE: This function panics if (x, y) is outside of the Image.
+    pub fn set_pixel(&mut self, x: u32, y: u32, red: u8, green: u8, blue: u8, alpha: u8) {
+        let i = self.pixel_offset(x, y);
+        self.pixels[i..i + 4].copy_from_slice(&[red, green, blue, alpha]);
+        self.mark_dirty(shape::Rect::new(x as i32, y as i32, 1, 1));
+    }
+
+    /// Replace the pixels inside `rect` with `pixels`, which are RGBA bytes row by row, like
+    /// `from_pixels`.
+    ///
+    /// NOTE: This function panics if `rect` doesn't fit inside the Image, or if `pixels` is not
+    /// exactly the right size for `rect`.
+    pub fn update_region(&mut self, rect: shape::Rect, pixels: &[u8]) {
+        assert!(
+            rect.x() >= 0
+                && rect.y() >= 0
+                && rect.right() as u32 <= self.width
+                && rect.bottom() as u32 <= self.height,
+            "region is outside of the image"
+        );
+        let row_bytes = rect.width() as usize * 4;
+        assert_eq!(
+            pixels.len(),
+            row_bytes * rect.height() as usize,
+            "pixel data doesn't match the region size"
+        );
+        for row in 0..rect.height() {
+            let dst = self.pixel_offset(rect.x() as u32, rect.y() as u32 + row);
+            let src = row as usize * row_bytes;
+            self.pixels[dst..dst + row_bytes].copy_from_slice(&pixels[src..src + row_bytes]);
+        }
+        self.mark_dirty(rect);
+    }
+
+    /// Return a copy of every pixel in the Image, as RGBA bytes row by row.
+    pub fn to_pixels(&self) -> Vec<u8> {
+        self.pixels.clone()
+    }
+
+    /// Return a copy of the pixels inside `rect`, which must be inside the Image.
+    fn region_pixels(&self, rect: shape::Rect) -> Vec<u8> {
+        let row_bytes = rect.width() as usize * 4;
+        let mut pixels = Vec::with_capacity(row_bytes * rect.height() as usize);
+        for row in 0..rect.height() {
+            let start = self.pixel_offset(rect.x() as u32, rect.y() as u32 + row);
+            pixels.extend_from_slice(&self.pixels[start..start + row_bytes]);
+        }
+        pixels
+    }
+
+    fn pixel_offset(&self, x: u32, y: u32) -> usize {
+        assert!(
+            x < self.width && y < self.height,
+            "pixel ({}, {}) is outside of the image",
+            x,
+            y
+        );
+        (y as usize * self.width as usize + x as usize) * 4
+    }
+
+    fn mark_dirty(&mut self, rect: shape::Rect) {
+        // Nothing to do until there is a texture to update.
+        if self.texture.is_some() {
+            self.dirty = Some(match self.dirty {
+                Some(dirty) => dirty.union(rect),
+                None => rect,
+            });
+        }
+    }
+}
+
+/**
+ * Canvas is an off-screen image that you can draw onto.
+ *
+ * Create one with `Window::create_canvas`, draw onto it inside of `Window::with_target`, and then
+ * draw it onto the screen with `Window::draw_canvas`. Anything that doesn't change every frame,
+ * like the background of a level, can be drawn onto a Canvas once and then reused. Free a Canvas
+ * that is no longer needed with `Window::destroy_canvas`.
+ *
+ * Note that drawing partially transparent graphics onto a transparent Canvas blends them with
+ * the transparent black it starts out as, which darkens soft edges. Either clear the Canvas to a
+ * solid color first, or draw the Canvas with `BlendMode::Premultiplied`, which is the right mode
+ * for what ends up on it. The software renderer can't use that mode, so check the result of
+ * `Window::set_blend_mode` if it might be in use.
+ */
+pub struct Canvas {
+    texture: render::Texture,
+    width: u32,
+    height: u32,
+}
+
+impl Canvas {
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+}
+
+/**
+ * Font is a way to render text, loaded either from a specially formatted image or from a TrueType
+ * file.
+ *
+ * Image fonts have a couple of advantages: they are a little simpler, and they look exactly the
+ * way they were drawn, which suits pixel art. On the other hand, the font size is fixed by the
+ * file. To have two different font sizes, you have to create two different Fonts from two
+ * different files. These special images are also less widely available.
+ *
+ * This link describes how ImageFonts work: https://love2d.org/wiki/Tutorial:Fonts_and_Text
+ *
+ * TrueType fonts, loaded with `Window::load_ttf`, can be loaded at any size and can print any
+ * character in the file. Each character is drawn into the Font's image the first time it is
+ * printed. The image stops growing at 4096 pixels in either direction, and characters that no
+ * longer fit are treated like characters the Font doesn't have.
+ *
+ * AngelCode BMFonts, loaded with `Window::load_bmfont`, are images made by font tools like BMFont
+ * and Hiero, with a `.fnt` file that says where each character is and how to place it. Fonts that
+ * are spread over several pages have their pages laid out side by side in a grid in one image.
+ *
+ * Every kind of Font can be fine-tuned with letter spacing, kerning, a fallback character for
+ * characters it doesn't have, and the placement of individual characters. These can be set one at
+ * a time, or all at once from a JSON file kept next to the image. See `load_metrics`.
+ *
+ * Characters that a Font doesn't have can come from other Fonts instead, so that one Font can
+ * print several alphabets. See `add_fallback_font`. Right-to-left text, such as Hebrew and
+ * Arabic, is printed in the right order, with Arabic letters joined if the Font has their
+ * presentation forms.
+ */
+pub struct Font {
+    image: Image,
+    chars: HashMap<char, Glyph>,
+    height: u32,
+    baseline: u32,
+    letter_spacing: i32,
+    kerning: HashMap<(char, char), i32>,
+    fallback: Option<char>,
+    fallback_fonts: Vec<Font>,
+    truetype: Option<TrueTypeGlyphs>,
+    /// Where the next character that is added to the image goes, and the height of the row of
+    /// characters that it goes on.
+    next: (u32, u32),
+    row_height: u32,
+    /// Textures of images that the Font has outgrown. They are destroyed once the batch can no
+    /// longer be drawing from them.
+    old_textures: Vec<render::Texture>,
+    /// Changes whenever anything that affects the layout of text changes. No two Fonts share a
+    /// version, so `Text` can tell whether it has to be laid out again.
+    version: u64,
+}
+
+/// Where a character is in the Font's image, and where to put it when printing.
+#[derive(Copy, Clone)]
+struct Glyph {
+    /// The part of the image to draw, or None if the character has no pixels, like a space.
+    rect: Option<shape::Rect>,
+    /// Where the top-left corner of `rect` goes, relative to the pen position at the top of the
+    /// line, or on the baseline if `on_baseline` is set.
+    offset: (i32, i32),
+    /// How far the pen moves after the character.
+    advance: i32,
+    /// True for characters that are placed relative to the baseline, so that they move with it:
+    /// those drawn from a TrueType font or copied in from a fallback Font.
+    on_baseline: bool,
+}
+
+impl Glyph {
+    /// Return where the top-left corner of `rect` goes, relative to the pen position at the top
+    /// of a line with the given baseline.
+    fn offset_from_top(&self, baseline: u32) -> (i32, i32) {
+        if self.on_baseline {
+            (self.offset.0, self.offset.1 + baseline as i32)
+        } else {
+            self.offset
+        }
+    }
+}
+
+/// What a TrueType Font needs to add characters to its image.
+struct TrueTypeGlyphs {
+    font: truetype::TrueType,
+    scale: f32,
+}
+
+impl TrueTypeGlyphs {
+    /// Return how far the pen moves after the glyph, in pixels.
+    fn advance(&self, index: u16) -> i32 {
+        (self.font.advance(index) as f32 * self.scale).round() as i32
+    }
+}
+
+/// The gap left between characters that are added to a Font's image, so that they don't bleed
+/// into each other when scaled.
+const GLYPH_PADDING: u32 = 1;
+
+/// How big the image of a Font may grow as characters are added to it. Most graphics cards can't
+/// hold bigger textures than this.
+const MAX_FONT_IMAGE_SIZE: u32 = 4096;
+
+/// How far the pen moves for a character that the Font can't print.
+const MISSING_CHAR_ADVANCE: i32 = 5;
+
+impl Font {
+    /// Determine whether "ch" exists in this Font.
+    pub fn is_printable(&self, ch: char) -> bool {
+        self.known_char_width(ch).is_some()
+    }
+
+    /// Return the number of printable characters that the Font contains. For TrueType fonts, this
+    /// only counts the characters that have been printed so far.
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    /// Return the height of the Font. This is constant for every possible character, while the
+    /// individual character widths vary. Note that certain characters (such a single quote `'`)
+    /// might not actually take up all of `height`. However, no character may exceed this limit.
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    /// Return the width and height of the text if it were printed with this Font by
+    /// `Window::print`, without printing it. Every `\n` starts a new line.
+    pub fn measure(&self, text: &str) -> (u32, u32) {
+        let (_, width, height) = self.place_lines(text);
+        (width, height)
+    }
+
+    /// Return the position of each character of the text when it is printed on one line at
+    /// (x, y), and the x position just after it.
+    fn place(&self, text: &str, x: i32, y: i32) -> (Vec<(char, i32, i32)>, i32) {
+        let mut placed = vec![];
+        let mut pen = x;
+        let mut previous = None;
+        for ch in self.display_line(text).chars() {
+            if let Some(p) = previous {
+                pen += self.spacing(p, ch);
+            }
+            placed.push((ch, pen, y));
+            pen += self.char_width(ch);
+            previous = Some(ch);
+        }
+        (placed, pen)
+    }
+
+    /// Return the line of text the way it appears on the screen, with Arabic letters joined and
+    /// right-to-left text reordered. See the `text` module.
+    fn display_line<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        // Nothing before Hebrew needs either.
+        if line.chars().all(|ch| (ch as u32) < 0x0590) {
+            return Cow::Borrowed(line);
+        }
+        let shaped = text::shape_arabic(line, |ch| self.is_printable(ch));
+        Cow::Owned(text::display_order(&shaped))
+    }
+
+    /// Return the position of each character of the text when it is printed at (0, 0), and the
+    /// width and height it takes up. Every `\n` starts a new line.
+    fn place_lines(&self, text: &str) -> (Vec<(char, i32, i32)>, u32, u32) {
+        let mut placed = vec![];
+        let mut width = 0;
+        let mut y = 0;
+        for line in text::split_lines(text) {
+            let (line_placed, end) = self.place(line, 0, y);
+            placed.extend(line_placed);
+            width = width.max(end);
+            y += self.height as i32;
+        }
+        (placed, width as u32, y as u32)
+    }
+
+    /// Return where each placed character is in the image, and where it goes on the screen.
+    /// Characters with no pixels are left out.
+    fn glyph_rects(&mut self, placed: &[(char, i32, i32)]) -> Vec<(shape::Rect, shape::Rect)> {
+        // Find every character first, since TrueType fonts may have to draw them into the image.
+        let mut rects = vec![];
+        for &(ch, x, y) in placed {
+            let glyph = match self.glyph(ch) {
+                Some(g) => g,
+                None => continue,
+            };
+            if let Some(font_rect) = glyph.rect {
+                let offset = glyph.offset_from_top(self.baseline);
+                let rect = shape::Rect::new(
+                    x + offset.0,
+                    y + offset.1,
+                    font_rect.width(),
+                    font_rect.height(),
+                );
+                rects.push((font_rect, rect));
+            }
+        }
+        rects
+    }
+
+    /// Lay the text out for `Window::draw_text`, with its top-left corner at (0, 0). Every `\n`
+    /// starts a new line.
+    fn layout(&mut self, text: &str) -> TextLayout {
+        let (placed, width, height) = self.place_lines(text);
+        TextLayout {
+            font_version: self.version,
+            width,
+            height,
+            glyphs: self.glyph_rects(&placed),
+            drawn_to_canvas: false,
+        }
+    }
+
+    /// Return how far `print` moves along after each character of the text, in order. This
+    /// includes letter spacing and kerning with the character after it. The characters are taken
+    /// as written, so this only matches the printed text when it runs left to right and has no
+    /// Arabic letters to join.
+    pub fn advances(&self, text: &str) -> Vec<i32> {
+        let chars: Vec<char> = text.chars().collect();
+        chars
+            .iter()
+            .enumerate()
+            .map(|(i, &ch)| {
+                let spacing = chars.get(i + 1).map_or(0, |&next| self.spacing(ch, next));
+                self.char_width(ch) + spacing
+            })
+            .collect()
+    }
+
+    /// Return where each line starts and ends if the text is wrapped to fit inside `width`, the
+    /// same way as `Window::print_rect` wraps it. The lines are byte ranges of the text, so
+    /// `&text[range]` is the line itself. Spaces where a line is wrapped, and the `\n` at the end
+    /// of a line, are left out of the ranges.
+    pub fn wrap(&self, text: &str, width: u32) -> Vec<Range<usize>> {
+        text::wrap(text, Some(width as i32), self)
+            .into_iter()
+            .map(|line| line.start..line.end)
+            .collect()
+    }
+
+    /// Return the byte index of the character boundary in the text that is closest to `x`, where
+    /// the text starts at 0. Use this to place a text cursor where the mouse was clicked. The
+    /// text should be a single line of left-to-right text: in Hebrew or Arabic, `x` is measured
+    /// along the text as it was written, not as it is printed.
+    pub fn index_at_x(&self, text: &str, x: i32) -> usize {
+        let mut pen = 0;
+        let mut previous = None;
+        // An accented letter is one character to the reader, so the cursor never goes inside it.
+        for (i, cluster) in text::graphemes(text) {
+            if let Some(p) = previous {
+                pen += self.spacing(p, cluster.chars().next().unwrap());
+            }
+            let width = text::width(cluster, self);
+            if x < pen + width / 2 {
+                return i;
+            }
+            pen += width;
+            previous = cluster.chars().last();
+        }
+        text.len()
+    }
+
+    /// Return how far from the start of the text the character at byte index `index` is printed.
+    /// This is the opposite of `index_at_x`, and like it, only lines up with the printed text when
+    /// it runs left to right.
+    ///
+    /// NOTE: This function panics if `index` is not on a character boundary of the text.
+    pub fn x_at_index(&self, text: &str, index: usize) -> i32 {
+        let (before, after) = text.split_at(index);
+        let spacing = match (before.chars().last(), after.chars().next()) {
+            (Some(left), Some(right)) => self.spacing(left, right),
+            _ => 0,
+        };
+        text::width(before, self) + spacing
+    }
+
+    /// Return how far `print` moves along after `ch`, not counting letter spacing or kerning.
+    /// Characters that the Font can't print are replaced by the fallback character, or leave a
+    /// little space if there is none.
+    pub fn char_width(&self, ch: char) -> i32 {
+        match self.known_char_width(ch) {
+            Some(width) => width,
+            // Accents that the Font doesn't have are left out, rather than leaving a gap.
+            None if text::is_combining(ch) => 0,
+            None => self
+                .fallback
+                .and_then(|f| self.known_char_width(f))
+                .unwrap_or(MISSING_CHAR_ADVANCE),
+        }
+    }
+
+    /// Return how far `print` moves along after `ch`, or None if the Font can't print it.
+    fn known_char_width(&self, ch: char) -> Option<i32> {
+        if let Some(glyph) = self.chars.get(&ch) {
+            return Some(glyph.advance);
+        }
+        if let Some(ref truetype) = self.truetype {
+            match truetype.font.glyph_index(ch) {
+                0 => (),
+                index => return Some(truetype.advance(index)),
+            }
+        }
+        self.fallback_fonts
+            .iter()
+            .find_map(|font| font.known_char_width(ch))
+    }
+
+    /// Return the distance from the top of a line to the baseline that the characters stand on.
+    /// This lines up text printed in different Fonts.
+    pub fn get_baseline(&self) -> u32 {
+        self.baseline
+    }
+
+    /// Set the distance from the top of a line to the baseline. For image fonts, this defaults to
+    /// the height of the Font.
+    pub fn set_baseline(&mut self, baseline: u32) {
+        self.version = next_font_version();
+        self.baseline = baseline;
+    }
+
+    /// Add `spacing` pixels between every two characters. This may be negative, to squeeze
+    /// characters together. The default is 0.
+    pub fn set_letter_spacing(&mut self, spacing: i32) {
+        self.version = next_font_version();
+        self.letter_spacing = spacing;
+    }
+
+    /// Return the spacing set by `set_letter_spacing`.
+    pub fn get_letter_spacing(&self) -> i32 {
+        self.letter_spacing
+    }
+
+    /// Print `ch` in place of characters that the Font doesn't have, such as `'?'`. The default,
+    /// `None`, leaves a little space instead.
+    pub fn set_fallback_char(&mut self, ch: Option<char>) {
+        self.version = next_font_version();
+        self.fallback = ch;
+    }
+
+    /// Return the character set by `set_fallback_char`.
+    pub fn get_fallback_char(&self) -> Option<char> {
+        self.fallback
+    }
+
+    /// Print characters that this Font doesn't have in `font` instead, such as a TrueType font
+    /// with Japanese characters behind a pixel-art image font. Fallback Fonts are tried in the
+    /// order they were added, before the fallback character. Their characters are copied into
+    /// this Font's image the first time they are printed, lined up on this Font's baseline.
+    pub fn add_fallback_font(&mut self, font: Font) {
+        self.fallback_fonts.push(font);
+        self.version = next_font_version();
+    }
+
+    /// Move `right` by `amount` pixels whenever it is printed right after `left`. Negative amounts
+    /// pull pairs like "AV" closer together.
+    pub fn set_kerning(&mut self, left: char, right: char, amount: i32) {
+        self.version = next_font_version();
+        if amount == 0 {
+            self.kerning.remove(&(left, right));
+        } else {
+            self.kerning.insert((left, right), amount);
+        }
+    }
+
+    /// Return the kerning set by `set_kerning` for the pair, or 0.
+    pub fn get_kerning(&self, left: char, right: char) -> i32 {
+        self.kerning.get(&(left, right)).cloned().unwrap_or(0)
+    }
+
+    /// Change where `ch` is drawn and how far the pen moves after it. `offset` moves the
+    /// character relative to the pen position at the top of the line; a negative x lets
+    /// characters like 'j' reach back under the one before them.
+    pub fn set_char_metrics(
+        &mut self,
+        ch: char,
+        offset: (i32, i32),
+        advance: i32,
+    ) -> Result<(), String> {
+        let mut glyph = self
+            .own_glyph(ch)
+            .ok_or_else(|| format!("font has no character {:?}", ch))?;
+        glyph.offset = if glyph.on_baseline {
+            (offset.0, offset.1 - self.baseline as i32)
+        } else {
+            offset
+        };
+        glyph.advance = advance;
+        self.chars.insert(ch, glyph);
+        self.version = next_font_version();
+        Ok(())
+    }
+
+    /// Set letter spacing, kerning, the fallback character, the baseline and the placement of
+    /// individual characters from JSON like this, where every field is optional:
+    ///
+    /// ```json
+    /// {
+    ///     "letter_spacing": 1,
+    ///     "baseline": 10,
+    ///     "fallback": "?",
+    ///     "kerning": { "AV": -2, "To": -1 },
+    ///     "chars": { "j": { "offset": [-1, 0], "advance": 3 } }
+    /// }
+    /// ```
+    ///
+    /// Nothing is changed if the JSON has a mistake in it.
+    pub fn load_metrics(&mut self, json: &str) -> Result<(), String> {
+        let metrics = FontMetrics::parse(json)?;
+        let mut glyphs = Vec::with_capacity(metrics.chars.len());
+        for &(ch, offset, advance) in &metrics.chars {
+            let glyph = self
+                .own_glyph(ch)
+                .ok_or_else(|| format!("font has no character {:?}", ch))?;
+            glyphs.push((ch, glyph, offset, advance));
+        }
+
+        if let Some(spacing) = metrics.letter_spacing {
+            self.letter_spacing = spacing;
+        }
+        if let Some(baseline) = metrics.baseline {
+            self.baseline = baseline;
+        }
+        if metrics.fallback.is_some() {
+            self.fallback = metrics.fallback;
+        }
+        for &(left, right, amount) in &metrics.kerning {
+            self.set_kerning(left, right, amount);
+        }
+        for (ch, mut glyph, offset, advance) in glyphs {
+            if let Some(offset) = offset {
+                glyph.offset = if glyph.on_baseline {
+                    (offset.0, offset.1 - self.baseline as i32)
+                } else {
+                    offset
+                };
+            }
+            glyph.advance = advance.unwrap_or(glyph.advance);
+            self.chars.insert(ch, glyph);
+        }
+        self.version = next_font_version();
+        Ok(())
+    }
+
+    /// Return how to draw `ch` if the Font or one of its fallback Fonts has it, rather than the
+    /// fallback character.
+    fn own_glyph(&mut self, ch: char) -> Option<Glyph> {
+        self.known_char_width(ch)?;
+        self.glyph(ch)
+    }
+
+    /// Return how to draw `ch`, drawing it into the image first if necessary. If the character
+    /// can't be drawn by this Font, return None.
+    fn glyph(&mut self, ch: char) -> Option<Glyph> {
+        if let Some(&glyph) = self.chars.get(&ch) {
+            return Some(glyph);
+        }
+        let (bitmap, advance) = {
+            let truetype = match self.truetype {
+                Some(ref truetype) => truetype,
+                None => return self.missing_glyph(ch),
+            };
+            let index = truetype.font.glyph_index(ch);
+            if index == 0 {
+                return self.missing_glyph(ch);
+            }
+            let outline = truetype.font.outline(index);
+            (
+                truetype::rasterize(&outline, truetype.scale),
+                truetype.advance(index),
+            )
+        };
+
+        let mut glyph = Glyph {
+            rect: None,
+            offset: bitmap.offset,
+            advance,
+            on_baseline: true,
+        };
+        if bitmap.width > 0 && bitmap.height > 0 {
+            let rect = self.allocate(bitmap.width, bitmap.height)?;
+            let mut pixels = Vec::with_capacity(bitmap.coverage.len() * 4);
+            for &alpha in &bitmap.coverage {
+                pixels.extend_from_slice(&[255, 255, 255, alpha]);
+            }
+            self.image.update_region(rect, &pixels);
+            glyph.rect = Some(rect);
+        }
+        self.chars.insert(ch, glyph);
+        Some(glyph)
+    }
+
+    /// Return how to draw `ch` when the Font doesn't have it: copied in from the first fallback
+    /// Font that has it, or else as the fallback character.
+    fn missing_glyph(&mut self, ch: char) -> Option<Glyph> {
+        let found = self
+            .fallback_fonts
+            .iter_mut()
+            .find(|font| font.known_char_width(ch).is_some());
+        let (mut glyph, pixels) = match found {
+            Some(font) => {
+                let glyph = font.glyph(ch)?;
+                let pixels = glyph.rect.map(|rect| font.image.region_pixels(rect));
+                let top = glyph.offset_from_top(font.baseline);
+                let offset = (top.0, top.1 - font.baseline as i32);
+                let glyph = Glyph {
+                    offset,
+                    on_baseline: true,
+                    ..glyph
+                };
+                (glyph, pixels)
+            }
+            None => return self.fallback_glyph(ch),
+        };
+        if let (Some(source), Some(pixels)) = (glyph.rect, pixels) {
+            let rect = self.allocate(source.width(), source.height())?;
+            self.image.update_region(rect, &pixels);
+            glyph.rect = Some(rect);
+        }
+        self.chars.insert(ch, glyph);
+        Some(glyph)
+    }
+
+    /// Return how to draw the fallback character in place of `ch`, if there is one.
+    fn fallback_glyph(&mut self, ch: char) -> Option<Glyph> {
+        match self.fallback {
+            Some(fallback)
+                if fallback != ch
+                    && !text::is_combining(ch)
+                    && self.known_char_width(fallback).is_some() =>
+            {
+                self.glyph(fallback)
+            }
+            _ => None,
+        }
+    }
+
+    /// Find room for a `width * height` character that is added to the image, making the image
+    /// bigger if it is full. Return None if the image would grow past `MAX_FONT_IMAGE_SIZE`.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<shape::Rect> {
+        let (mut x, mut y) = self.next;
+        let mut row_height = self.row_height;
+        if x > 0 && x + width > self.image.width {
+            x = 0;
+            y += row_height + GLYPH_PADDING;
+            row_height = 0;
+        }
+
+        let (new_width, new_height) = (
+            grown_size(self.image.width, width)?,
+            grown_size(self.image.height, y.checked_add(height)?)?,
+        );
+        if (new_width, new_height) != (self.image.width, self.image.height) {
+            let mut image = Image::new(new_width, new_height);
+            let old = &self.image;
+            let old_rect = shape::Rect::new(0, 0, old.width, old.height);
+            image.update_region(old_rect, &old.pixels);
+            let old = std::mem::replace(&mut self.image, image);
+            // The batch may still be drawing from the old texture, so it can't be destroyed yet.
+            self.old_textures.extend(old.texture);
+        }
+
+        self.next = (x + width + GLYPH_PADDING, y);
+        self.row_height = row_height.max(height);
+        Some(shape::Rect::new(x as i32, y as i32, width, height))
+    }
+
+    /// Take the textures that this Font and its fallback Fonts have outgrown.
+    fn take_old_textures(&mut self) -> Vec<render::Texture> {
+        let mut textures = std::mem::take(&mut self.old_textures);
+        for font in &mut self.fallback_fonts {
+            textures.extend(font.take_old_textures());
+        }
+        textures
+    }
+}
+
+/// Return `size`, doubled until it is at least `needed`, or None if that is more than
+/// `MAX_FONT_IMAGE_SIZE`.
+fn grown_size(size: u32, needed: u32) -> Option<u32> {
+    if needed <= size {
+        return Some(size);
+    }
+    let mut size = size.max(1);
+    while size < needed {
+        size *= 2;
+        if size > MAX_FONT_IMAGE_SIZE {
+            return None;
+        }
+    }
+    Some(size)
+}
+
+/// Return a Font version that hasn't been used before.
+fn next_font_version() -> u64 {
+    static NEXT_VERSION: AtomicU64 = AtomicU64::new(0);
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed)
+}
+
+impl text::Metrics for Font {
+    fn char_width(&self, ch: char) -> i32 {
+        Font::char_width(self, ch)
+    }
+
+    fn spacing(&self, left: char, right: char) -> i32 {
+        // Accents belong to the character before them, so they don't get spaced apart from it.
+        if text::continues_grapheme(left, right) {
+            return 0;
+        }
+        self.letter_spacing + self.get_kerning(left, right)
+    }
+
+    fn shaped_width(&self, line: &str) -> Option<i32> {
+        match self.display_line(line) {
+            Cow::Borrowed(_) => None,
+            Cow::Owned(shown) => Some(text::width(&shown, self)),
+        }
+    }
+}
+
+/**
+ * MarkupStyle holds the fonts and images that markup can refer to by name. See
+ * `Window::print_markup`.
+ *
+ * Text outside of a `[font]` tag is printed in the Window's own font, so a style only needs the
+ * extra fonts, like a bold one for `[font=bold]`.
+ */
+#[derive(Default)]
+pub struct MarkupStyle {
+    fonts: HashMap<String, Font>,
+    images: HashMap<String, Image>,
+}
+
+impl MarkupStyle {
+    pub fn new() -> MarkupStyle {
+        MarkupStyle::default()
+    }
+
+    /// Make the font available to `[font=name]` tags, replacing any font with the same name.
+    pub fn add_font(&mut self, name: &str, font: Font) {
+        self.fonts.insert(name.to_string(), font);
+    }
+
+    /// Make the image available to `[img=name]` tags, replacing any image with the same name.
+    pub fn add_image(&mut self, name: &str, image: Image) {
+        self.images.insert(name.to_string(), image);
+    }
+
+    pub fn get_font_mut(&mut self, name: &str) -> Option<&mut Font> {
+        self.fonts.get_mut(name)
+    }
+
+    pub fn get_image_mut(&mut self, name: &str) -> Option<&mut Image> {
+        self.images.get_mut(name)
+    }
+
+    fn image(&self, name: &str) -> Result<&Image, String> {
+        self.images
+            .get(name)
+            .ok_or_else(|| format!("markup style has no image named {:?}", name))
+    }
+}
+
+/**
+ * Text is a string that is laid out once and then drawn as often as needed with
+ * `Window::draw_text`. That is cheaper than calling `print` every frame, which suits HUDs with
+ * lots of labels that rarely change.
+ *
+ * The layout is worked out again only when the string changes, or when the Window's font is
+ * replaced or has its spacing, kerning or other metrics changed. The Window's color can change
+ * freely.
+ *
+ * ```rust,no_run
+ * # let mut app = simple::Window::new("hello world", 640, 480);
+ * let mut label = simple::Text::new("Score: 0");
+ * let mut score = 0;
+ * while app.next_frame() {
+ *     label.set_text(&format!("Score: {}", score));
+ *     app.draw_text(&mut label, 10, 10);
+ * }
+ * ```
+ */
+pub struct Text {
+    string: String,
+    to_texture: bool,
+    layout: Option<TextLayout>,
+    /// The text drawn in white, when it is drawn through a texture. It is kept when the text
+    /// changes, and reused if the new text fits.
+    canvas: Option<Canvas>,
+}
+
+/// Where every character of a Text goes, relative to its top-left corner.
+struct TextLayout {
+    font_version: u64,
+    width: u32,
+    height: u32,
+    /// Where each character is in the Font's image, and where it goes.
+    glyphs: Vec<(shape::Rect, shape::Rect)>,
+    drawn_to_canvas: bool,
+}
+
+impl Text {
+    pub fn new(text: &str) -> Text {
+        Text {
+            string: text.to_string(),
+            to_texture: false,
+            layout: None,
+            canvas: None,
+        }
+    }
+
+    /// Change the string. Nothing is laid out again if it is the same as before.
+    pub fn set_text(&mut self, text: &str) {
+        if self.string != text {
+            self.string = text.to_string();
+            self.layout = None;
+        }
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.string
+    }
+
+    /// Draw the text onto an off-screen Canvas once, and then draw that Canvas instead of each
+    /// character. This makes long texts cheaper to draw, at the cost of some graphics memory.
+    /// The Canvas is drawn with `BlendMode::Premultiplied`, whatever the Window's BlendMode. If
+    /// the renderer can't draw onto textures or use that BlendMode, the text is drawn one
+    /// character at a time anyway.
+    pub fn set_render_to_texture(&mut self, to_texture: bool) {
+        self.to_texture = to_texture;
+    }
+
+    pub fn get_render_to_texture(&self) -> bool {
+        self.to_texture
+    }
+}
+
+/// A piece of markup with its position worked out. See `Window::layout_markup`.
+enum PlacedPiece<'a> {
+    Text {
+        font: Option<&'a str>,
+        color: Option<Color>,
+        chars: Vec<(char, i32, i32)>,
+    },
+    Image {
+        name: &'a str,
+        x: i32,
+        y: i32,
+    },
+}
+
+type CharMetrics = (char, Option<(i32, i32)>, Option<i32>);
+
+/// Everything that `Font::load_metrics` reads, checked before any of it is applied.
+#[derive(Default)]
+struct FontMetrics {
+    letter_spacing: Option<i32>,
+    baseline: Option<u32>,
+    fallback: Option<char>,
+    kerning: Vec<(char, char, i32)>,
+    /// Each character with its new offset from the top of the line and advance, if given.
+    chars: Vec<CharMetrics>,
+}
+
+impl FontMetrics {
+    fn parse(json: &str) -> Result<FontMetrics, String> {
+        let json: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let fields = json
+            .as_object()
+            .ok_or_else(|| "font metrics must be a JSON object".to_string())?;
+        let mut metrics = FontMetrics::default();
+        for (key, value) in fields {
+            match key.as_str() {
+                "letter_spacing" => metrics.letter_spacing = Some(json_i32(value, key)?),
+                "baseline" => {
+                    let baseline = json_i32(value, key)?;
+                    if baseline < 0 {
+                        return Err("font metric \"baseline\" can't be negative".to_string());
+                    }
+                    metrics.baseline = Some(baseline as u32);
+                }
+                "fallback" => metrics.fallback = Some(json_chars(value, key, 1)?[0]),
+                "kerning" => {
+                    for (pair, amount) in json_object(value, key)? {
+                        let pair_chars = json_chars(&Value::String(pair.clone()), key, 2)?;
+                        let amount = json_i32(amount, pair)?;
+                        metrics.kerning.push((pair_chars[0], pair_chars[1], amount));
+                    }
+                }
+                "chars" => {
+                    for (ch, char_metrics) in json_object(value, key)? {
+                        let ch = json_chars(&Value::String(ch.clone()), key, 1)?[0];
+                        let offset = match char_metrics.get("offset") {
+                            Some(Value::Array(xy)) if xy.len() == 2 => {
+                                Some((json_i32(&xy[0], "offset")?, json_i32(&xy[1], "offset")?))
+                            }
+                            Some(_) => return Err("offset must be an [x, y] array".to_string()),
+                            None => None,
+                        };
+                        let advance = match char_metrics.get("advance") {
+                            Some(advance) => Some(json_i32(advance, "advance")?),
+                            None => None,
+                        };
+                        metrics.chars.push((ch, offset, advance));
+                    }
+                }
+                _ => return Err(format!("unknown font metric {:?}", key)),
+            }
+        }
+        Ok(metrics)
+    }
+}
+
+fn json_object<'a>(json: &'a Value, name: &str) -> Result<&'a Map<String, Value>, String> {
+    json.as_object()
+        .ok_or_else(|| format!("font metric {:?} must be an object", name))
+}
+
+fn json_i32(json: &Value, name: &str) -> Result<i32, String> {
+    json.as_i64()
+        .and_then(|n| i32::try_from(n).ok())
+        .ok_or_else(|| {
+            format!(
+                "font metric {:?} must be a whole number that fits in an i32",
+                name
+            )
+        })
+}
+
+/// Return the characters of a JSON string that must be exactly `count` characters long.
+fn json_chars(json: &Value, name: &str, count: usize) -> Result<Vec<char>, String> {
+    let chars: Vec<char> = json.as_str().unwrap_or("").chars().collect();
+    if chars.len() != count {
+        return Err(format!(
+            "font metric {:?} must be {} character(s), not {}",
+            name, count, json
+        ));
+    }
+    Ok(chars)
+}
+
+/// Return the width, height and RGBA pixels of the Surface, row by row.
+fn surface_pixels(mut surf: surface::Surface) -> Result<(u32, u32, Vec<u8>), String> {
+    // Blit onto an RGBA surface to convert the pixel format. Blending has to be off, or the
+    // colors get mixed with the empty destination.
+    let (width, height) = surf.size();
+    let mut rgba = surface::Surface::new(width, height, pixels::PixelFormatEnum::RGBA32)?;
+    surf.set_blend_mode(render::BlendMode::None)?;
+    surf.blit(None, &mut rgba, None)?;
+
+    let pitch = rgba.pitch() as usize;
+    let row_bytes = width as usize * 4;
+    let mut data = Vec::with_capacity(row_bytes * height as usize);
+    rgba.with_lock(|pixels| {
+        for row in 0..height as usize {
+            data.extend_from_slice(&pixels[row * pitch..row * pitch + row_bytes]);
+        }
+    });
+
+    Ok((width, height, data))
+}
+
+/// Find the characters of an image font in its RGBA pixels, row by row, and match them up with
+/// the characters of `string`, in order. See the documentation on `Font` for the format: the
+/// color of the top-left pixel separates the characters, and wherever the top row of pixels is
+/// any other color, there is a character.
+fn parse_image_font_glyphs(
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+    string: &str,
+) -> Result<HashMap<char, Glyph>, String> {
+    if util::rgba_len(width, height) != Some(pixels.len()) {
+        return Err("image font pixel data doesn't match its size".to_string());
+    }
+    if width == 0 || height == 0 {
+        return Err("image font is empty".to_string());
+    }
+    if util::string_has_duplicate_chars(string.to_string()) {
+        return Err("image font string has duplicate characters".to_string());
+    }
+
+    // Each run of columns whose top pixel isn't the separator color is one character.
+    let separator = &pixels[0..4];
+    let mut columns = vec![];
+    let mut start = None;
+    for x in 0..width as usize {
+        let is_separator = &pixels[x * 4..x * 4 + 4] == separator;
+        match (start, is_separator) {
+            (None, false) => start = Some(x),
+            (Some(first), true) => {
+                columns.push(first..x);
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    if let Some(first) = start {
+        columns.push(first..width as usize);
+    }
+
+    let count = string.chars().count();
+    if columns.len() != count {
+        return Err(format!(
+            "image font has {} characters, but its string has {}",
+            columns.len(),
+            count
+        ));
+    }
+    let chars = string
+        .chars()
+        .zip(columns)
+        .map(|(ch, range)| {
+            let rect = shape::Rect::new(range.start as i32, 0, range.len() as u32, height);
+            let glyph = Glyph {
+                rect: Some(rect),
+                offset: (0, 0),
+                advance: rect.width() as i32,
+                on_baseline: false,
+            };
+            (ch, glyph)
+        })
+        .collect();
+    Ok(chars)
+}
+
+/// This is the default font.
+const DEFAULT_FONT_BYTES: &'static [u8] = include_bytes!("default_font.png");
+const DEFAULT_FONT_STR: &'static str =
+    " abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789.,!?-+/():;%&`'*#=[]\"";
+
+/// Resource Loading Methods
+/// ========================
+impl Window {
+    /// Load the image at the path you specify.
+    pub fn load_image_from_file(&self, filename: &Path) -> Result<Image, String> {
+        let surf: surface::Surface = LoadSurface::from_file(filename)?;
+        self.image_from_surface(surf)
+    }
+
+    /// Load an image from a slice of bytes. This function is particularly powerful when
+    /// used in conjunction with the `include_bytes` macro that embeds data in the compiled
+    /// executable. In this way, you can pack all of your game data into your executable.
+    pub fn load_image(&self, data: &[u8]) -> Result<Image, String> {
+        let rwops = rwops::RWops::from_bytes(data)?;
+        let surf: surface::Surface = rwops.load()?;
+        self.image_from_surface(surf)
+    }
+
+    /// Copy the pixels out of the Surface into a new Image.
+    fn image_from_surface(&self, surf: surface::Surface) -> Result<Image, String> {
+        let (width, height, data) = surface_pixels(surf)?;
+        let mut image = Image::from_pixels(width, height, &data);
+        self.upload_image(&mut image)?;
+        Ok(image)
+    }
+
+    /// Make sure the Image's texture exists and is up to date with its pixels.
+    fn upload_image(&self, image: &mut Image) -> Result<(), String> {
+        let pitch = image.width as usize * 4;
+        if image.texture.is_none() {
+            let mut texture = self
+                .canvas
+                .texture_creator()
+                .create_texture_static(pixels::PixelFormatEnum::RGBA32, image.width, image.height)
+                .map_err(|e| e.to_string())?;
+            texture
+                .update(None, &image.pixels, pitch)
+                .map_err(|e| e.to_string())?;
+            texture.set_blend_mode(render::BlendMode::Blend);
+            image.texture = Some(texture);
+            image.dirty = None;
+        }
+
+        let dirty = match image.dirty.take() {
+            Some(d) => d,
+            None => return Ok(()),
+        };
+        if !image.streaming {
+            // This Image is being edited after all, so move it to a texture meant for that.
+            let mut texture = self
+                .canvas
+                .texture_creator()
+                .create_texture_streaming(
+                    pixels::PixelFormatEnum::RGBA32,
+                    image.width,
+                    image.height,
+                )
+                .map_err(|e| e.to_string())?;
+            texture.set_blend_mode(render::BlendMode::Blend);
+            if let Some(old) = image.texture.replace(texture) {
+                // The canvas is alive, since we are borrowing it, so this is safe.
+                unsafe { old.destroy() };
+            }
+            image.streaming = true;
+        }
+
+        let start = image.pixel_offset(dirty.x() as u32, dirty.y() as u32);
+        let end = image.pixel_offset(dirty.right() as u32 - 1, dirty.bottom() as u32 - 1) + 4;
+        image
+            .texture
+            .as_mut()
+            .unwrap()
+            .update(dirty, &image.pixels[start..end], pitch)
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Load an Aseprite file from a slice of bytes. Visible layers are flattened, and every frame
+    /// becomes one sprite of the returned sheet. See `AsepriteFile` for the details.
+    pub fn load_aseprite(&self, data: &[u8]) -> Result<AsepriteFile, String> {
+        let doc = aseprite::parse(data)?;
+        if doc.frames.is_empty() || doc.width == 0 || doc.height == 0 {
+            return Err("aseprite file has no pixels".to_string());
+        }
+
+        // Lay the frames out in a square-ish grid, to stay inside texture size limits.
+        let columns = (doc.frames.len() as f64).sqrt().ceil() as u32;
+        let rows = (doc.frames.len() as u32 + columns - 1) / columns;
+        let sheet_width = columns
+            .checked_mul(doc.width)
+            .ok_or_else(|| "aseprite file is too big".to_string())?;
+        let sheet_len = rows
+            .checked_mul(doc.height)
+            .and_then(|height| util::rgba_len(sheet_width, height))
+            .ok_or_else(|| "aseprite file is too big".to_string())?;
+        let row_bytes = doc.width as usize * 4;
+        let mut pixels = vec![0; sheet_len];
+        let mut sprites = vec![];
+        for (i, frame) in doc.frames.iter().enumerate() {
+            let x = (i as u32 % columns) * doc.width;
+            let y = (i as u32 / columns) * doc.height;
+            for row in 0..doc.height {
+                let dst = ((y + row) as usize * sheet_width as usize + x as usize) * 4;
+                let src = row as usize * row_bytes;
+                pixels[dst..dst + row_bytes].copy_from_slice(&frame.pixels[src..src + row_bytes]);
+            }
+            let mut sprite =
+                Sprite::new(shape::Rect::new(x as i32, y as i32, doc.width, doc.height));
+            sprite.duration = Some(frame.duration);
+            sprites.push(sprite);
+        }
+
+        let mut image = Image::from_pixels(sheet_width, rows * doc.height, &pixels);
+        self.upload_image(&mut image)?;
+        let sheet = SpriteSheet::new(image, sprites);
+        let animations = doc
+            .tags
+            .iter()
+            .map(|tag| (tag.name.clone(), tag.to_animation(&sheet)))
+            .collect();
+        Ok(AsepriteFile { sheet, animations })
+    }
+
+    /// Load an Aseprite file from the hard drive. See `load_aseprite`.
+    pub fn load_aseprite_from_file(&self, filename: &Path) -> Result<AsepriteFile, String> {
+        let data = std::fs::read(filename).map_err(|e| e.to_string())?;
+        self.load_aseprite(&data)
+    }
+
+    /// Parse a font from the Surface, using the string as a guideline.
+    fn parse_image_font(&self, surf: surface::Surface, string: String) -> Result<Font, String> {
+        let (width, height, pixels) = surface_pixels(surf)?;
+        let chars = parse_image_font_glyphs(width, height, &pixels, &string)?;
+        let mut image = Image::from_pixels(width, height, &pixels);
+        self.upload_image(&mut image)?;
+        Ok(Font {
+            height,
+            image,
+            chars,
+            baseline: height,
+            letter_spacing: 0,
+            kerning: HashMap::new(),
+            fallback: None,
+            fallback_fonts: vec![],
+            truetype: None,
+            // Characters from fallback Fonts go below the ones in the image.
+            next: (0, height + GLYPH_PADDING),
+            row_height: 0,
+            old_textures: vec![],
+            version: next_font_version(),
+        })
+    }
+
+    /// Load a Font from the hard drive. See the documentation on `Font` for details.
+    pub fn load_font_from_file(&self, filename: &Path, string: String) -> Result<Font, String> {
+        let surf: surface::Surface = try!(LoadSurface::from_file(filename));
+        self.parse_image_font(surf, string)
+    }
+
+    /// Load a Font from a slice of bytes. See the documentation on `Font` for details. This
+    /// function is particularly powerful when used in conjunction with the `include_bytes` macro
+    /// that embeds data in the compiled executable.
     pub fn load_font(&self, data: &[u8], string: String) -> Result<Font, String> {
         let rwops = try!(rwops::RWops::from_bytes(data));
         let surf: surface::Surface = try!(rwops.load());
         self.parse_image_font(surf, string)
     }
+
+    /// Load a TrueType font from a slice of bytes, `size` pixels tall. Any character in the font
+    /// can be printed. See the documentation on `Font` for details.
+    pub fn load_ttf(&self, data: &[u8], size: f32) -> Result<Font, String> {
+        if !(size > 0.0 && size.is_finite()) {
+            return Err(format!("invalid font size {}", size));
+        }
+        let font = truetype::TrueType::parse(data)?;
+        let scale = font.scale_for_size(size);
+        let (ascent, descent, _) = font.vertical_metrics();
+        let baseline = (ascent as f32 * scale).round() as i32;
+        let height = ((ascent as f32 - descent as f32) * scale).ceil().max(1.0) as u32;
+
+        // Room for a few hundred characters to start with. The image grows when it is full.
+        let side = (size.ceil() as u32 * 16).next_power_of_two().min(2048);
+        Ok(Font {
+            image: Image::new(side, side),
+            chars: HashMap::new(),
+            height,
+            baseline: baseline.max(0) as u32,
+            letter_spacing: 0,
+            kerning: HashMap::new(),
+            fallback: None,
+            fallback_fonts: vec![],
+            truetype: Some(TrueTypeGlyphs { font, scale }),
+            next: (0, 0),
+            row_height: 0,
+            old_textures: vec![],
+            version: next_font_version(),
+        })
+    }
+
+    /// Load a TrueType font from the hard drive. See `load_ttf`.
+    pub fn load_ttf_from_file(&self, filename: &Path, size: f32) -> Result<Font, String> {
+        let data = std::fs::read(filename).map_err(|e| e.to_string())?;
+        self.load_ttf(&data, size)
+    }
+
+    /// Load an AngelCode BMFont, as exported by tools like BMFont and Hiero. `descriptor` is the
+    /// `.fnt` file, in its text, XML or binary form, and `pages` are the images it names, in page
+    /// order. Kerning pairs and the baseline come from the descriptor. See the documentation on
+    /// `Font` for details.
+    pub fn load_bmfont(&self, descriptor: &[u8], pages: &[&[u8]]) -> Result<Font, String> {
+        let doc = bmfont::parse(descriptor)?;
+        if pages.len() != doc.pages.len() {
+            return Err(format!(
+                "BMFont has {} pages, but {} images were given",
+                doc.pages.len(),
+                pages.len()
+            ));
+        }
+        let mut surfaces = vec![];
+        for &page in pages {
+            let rwops = rwops::RWops::from_bytes(page)?;
+            surfaces.push(surface_pixels(rwops.load()?)?);
+        }
+        self.bmfont_from_pages(doc, surfaces)
+    }
+
+    /// Load an AngelCode BMFont from the hard drive. The page images are loaded from the same
+    /// folder as the `.fnt` file. See `load_bmfont`.
+    pub fn load_bmfont_from_file(&self, filename: &Path) -> Result<Font, String> {
+        let data = std::fs::read(filename).map_err(|e| e.to_string())?;
+        let doc = bmfont::parse(&data)?;
+        let folder = filename.parent().unwrap_or_else(|| Path::new(""));
+        let mut surfaces = vec![];
+        for page in &doc.pages {
+            let surf: surface::Surface = LoadSurface::from_file(folder.join(page))?;
+            surfaces.push(surface_pixels(surf)?);
+        }
+        self.bmfont_from_pages(doc, surfaces)
+    }
+
+    /// Lay the pages of a BMFont out in a grid in one image, so that the whole Font can be drawn
+    /// from one texture.
+    fn bmfont_from_pages(
+        &self,
+        doc: bmfont::Document,
+        pages: Vec<(u32, u32, Vec<u8>)>,
+    ) -> Result<Font, String> {
+        let sizes: Vec<(u32, u32)> = pages.iter().map(|p| (p.0, p.1)).collect();
+        let grid = page_grid(&sizes).ok_or_else(|| "BMFont pages are too big".to_string())?;
+        let mut image = Image::new(grid.width.max(1), grid.height.max(1));
+        for (&(x, y), (page_width, page_height, pixels)) in grid.corners.iter().zip(pages) {
+            let rect = shape::Rect::new(x as i32, y as i32, page_width, page_height);
+            image.update_region(rect, &pixels);
+        }
+
+        let mut chars = HashMap::new();
+        for c in &doc.chars {
+            let (page_width, page_height) = sizes[c.page];
+            let fits = |start: u32, len: u32, page_len| {
+                start.checked_add(len).is_some_and(|end| end <= page_len)
+            };
+            if !fits(c.x, c.width, page_width) || !fits(c.y, c.height, page_height) {
+                return Err(format!(
+                    "BMFont character {:?} is outside of its page",
+                    c.ch
+                ));
+            }
+            let rect = if c.width == 0 || c.height == 0 {
+                None
+            } else {
+                let (page_x, page_y) = grid.corners[c.page];
+                let (x, y) = ((page_x + c.x) as i32, (page_y + c.y) as i32);
+                Some(shape::Rect::new(x, y, c.width, c.height))
+            };
+            chars.insert(
+                c.ch,
+                Glyph {
+                    rect,
+                    offset: c.offset,
+                    advance: c.advance,
+                    on_baseline: false,
+                },
+            );
+        }
+
+        let mut kerning = HashMap::new();
+        for &(left, right, amount) in &doc.kerning {
+            if amount != 0 {
+                kerning.insert((left, right), amount);
+            }
+        }
+
+        self.upload_image(&mut image)?;
+        let next = (0, image.height + GLYPH_PADDING);
+        Ok(Font {
+            image,
+            chars,
+            height: doc.line_height,
+            baseline: doc.base,
+            letter_spacing: 0,
+            kerning,
+            fallback: None,
+            fallback_fonts: vec![],
+            truetype: None,
+            next,
+            row_height: 0,
+            old_textures: vec![],
+            version: next_font_version(),
+        })
+    }
+}
+
+/// Where the pages of a BMFont go in its image.
+#[derive(Debug, PartialEq)]
+struct PageGrid {
+    width: u32,
+    height: u32,
+    /// The top-left corner of each page.
+    corners: Vec<(u32, u32)>,
+}
+
+/// Lay out pages of the given sizes in a grid of equal cells that is about as wide as it is tall.
+/// Return None if the grid is too big to fit in an Image.
+fn page_grid(sizes: &[(u32, u32)]) -> Option<PageGrid> {
+    let cell_width = sizes.iter().map(|s| s.0).max().unwrap_or(0);
+    let cell_height = sizes.iter().map(|s| s.1).max().unwrap_or(0);
+    let count = sizes.len() as u32;
+    let mut columns = 1;
+    while columns * columns < count {
+        columns += 1;
+    }
+    let rows = (count + columns - 1) / columns;
+    let width = cell_width.checked_mul(columns)?;
+    let height = cell_height.checked_mul(rows)?;
+    util::rgba_len(width, height)?;
+    let corners = (0..count)
+        .map(|i| ((i % columns) * cell_width, (i / columns) * cell_height))
+        .collect();
+    Some(PageGrid {
+        width,
+        height,
+        corners,
+    })
+}
+
+#[test]
+fn image_pixels() {
+    let mut image = Image::new(3, 2);
+    assert_eq!(image.get_pixel(2, 1), (0, 0, 0, 0));
+
+    image.set_pixel(2, 1, 1, 2, 3, 4);
+    assert_eq!(image.get_pixel(2, 1), (1, 2, 3, 4));
+
+    image.update_region(shape::Rect::new(0, 0, 2, 1), &[9, 9, 9, 9, 8, 8, 8, 8]);
+    assert_eq!(image.get_pixel(1, 0), (8, 8, 8, 8));
+    assert_eq!(
+        image.to_pixels(),
+        vec![9, 9, 9, 9, 8, 8, 8, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4]
+    );
+
+    // nothing to update until the image has a texture
+    assert_eq!(image.dirty, None);
+}
+
+#[test]
+#[should_panic]
+fn image_pixel_out_of_bounds() {
+    Image::new(3, 2).get_pixel(3, 0);
+}
+
+#[test]
+#[should_panic(expected = "doesn't match the image size")]
+fn image_size_overflow() {
+    // 0x4000_0000 * 4 * 4 wraps around to 0 in 32 bits
+    Image::from_pixels(0x4000_0000, 4, &[]);
+}
+
+#[test]
+fn logical_viewport_modes() {
+    // 320x180 into 1000x600: 3x whole, 3.125x fitted by width
+    assert_eq!(
+        logical_viewport((1000, 600), (320, 180), ScaleMode::IntegerScale),
+        (20.0, 30.0, 3.0, 3.0)
+    );
+    assert_eq!(
+        logical_viewport((1000, 600), (320, 180), ScaleMode::Fit),
+        (0.0, 19.0, 3.125, 3.125)
+    );
+    assert_eq!(
+        logical_viewport((1000, 600), (320, 180), ScaleMode::Stretch),
+        (0.0, 0.0, 3.125, 600.0 / 180.0)
+    );
+
+    // integer scaling never shrinks below 1x
+    assert_eq!(
+        logical_viewport((100, 100), (320, 180), ScaleMode::IntegerScale),
+        (-110.0, -40.0, 1.0, 1.0)
+    );
+}
+
+#[cfg(test)]
+fn test_font() -> Font {
+    // 'i' is narrow and 'm' is wide; spaces are the width of 'i'
+    let glyph = |x, width| Glyph {
+        rect: Some(shape::Rect::new(x, 0, width, 8)),
+        offset: (0, 0),
+        advance: width as i32,
+        on_baseline: false,
+    };
+    let mut chars = HashMap::new();
+    chars.insert('i', glyph(0, 2));
+    chars.insert(' ', glyph(2, 2));
+    chars.insert('m', glyph(4, 6));
+    Font {
+        image: Image::new(10, 8),
+        chars,
+        height: 8,
+        baseline: 8,
+        letter_spacing: 0,
+        kerning: HashMap::new(),
+        fallback: None,
+        fallback_fonts: vec![],
+        truetype: None,
+        next: (0, 9),
+        row_height: 0,
+        old_textures: vec![],
+        version: next_font_version(),
+    }
+}
+
+#[test]
+fn font_measure() {
+    let font = test_font();
+    assert_eq!(font.measure("mim"), (14, 8));
+    assert_eq!(font.measure("i\nmm\n"), (12, 24));
+    assert_eq!(font.measure(""), (0, 8));
+    // Windows line endings don't leave anything at the end of the line
+    assert_eq!(font.measure("mi\r\ni\r\n"), font.measure("mi\ni\n"));
+    // characters the font doesn't have still take up a little room
+    assert_eq!(font.advances("im?"), vec![2, 6, MISSING_CHAR_ADVANCE]);
+    assert_eq!(font.wrap("mm mm i", 15), vec![0..2, 3..5, 6..7]);
+
+    // measured the same way print lays the text out, spacing and all
+    let mut font = test_font();
+    font.set_letter_spacing(2);
+    font.set_kerning('m', 'i', -3);
+    assert_eq!(font.measure("mim\ni"), (15, 16));
+    let (_, width, height) = font.place_lines("mim\ni");
+    assert_eq!((width, height), (15, 16));
+}
+
+#[test]
+fn font_index_at_x() {
+    let font = test_font();
+    assert_eq!(font.index_at_x("mim", -5), 0);
+    assert_eq!(font.index_at_x("mim", 2), 0);
+    assert_eq!(font.index_at_x("mim", 3), 1);
+    assert_eq!(font.index_at_x("mim", 7), 2);
+    assert_eq!(font.index_at_x("mim", 100), 3);
+    assert_eq!(font.x_at_index("mim", 2), 8);
+}
+
+#[test]
+fn font_cursor_positions_match_print() {
+    let mut font = test_font();
+    font.set_letter_spacing(1);
+    font.set_kerning('m', 'i', -2);
+    let text = "mi mi";
+    let (placed, _) = font.place(text, 0, 0);
+    for ((index, _), &(_, x, _)) in text.char_indices().zip(&placed) {
+        assert_eq!(font.x_at_index(text, index), x);
+        assert_eq!(font.index_at_x(text, x), index);
+    }
+    let mut pen = 0;
+    for (advance, &(_, x, _)) in font.advances(text).into_iter().zip(&placed) {
+        assert_eq!(pen, x);
+        pen += advance;
+    }
+}
+
+#[test]
+fn font_shaped_widths() {
+    // lam and alef are narrow, but the font joins them into one wide character
+    let mut font = test_font();
+    let glyph = |width| Glyph {
+        rect: None,
+        offset: (0, 0),
+        advance: width,
+        on_baseline: false,
+    };
+    font.chars.insert('\u{644}', glyph(3));
+    font.chars.insert('\u{627}', glyph(2));
+    font.chars.insert('\u{FEFB}', glyph(8));
+    let lam_alef = "\u{644}\u{627}";
+    assert_eq!(font.measure(lam_alef), (8, 8));
+
+    // wrapped by how wide the words are printed, not how wide their letters are on their own
+    let text = format!("{} {}", lam_alef, lam_alef);
+    assert_eq!(font.wrap(&text, 13), vec![0..4, 5..9]);
+    assert_eq!(font.wrap(&text, 18), vec![0..9]);
+    let lines = text::wrap(&text, Some(13), &font);
+    assert_eq!(lines[0].width, 8);
+}
+
+#[test]
+fn font_metrics() {
+    let mut font = test_font();
+    font.load_metrics(
+        r#"{
+            "letter_spacing": 1,
+            "fallback": "i",
+            "kerning": { "mi": -3 },
+            "chars": { "m": { "advance": 7 } }
+        }"#,
+    )
+    .unwrap();
+    // 7 for 'm', 1 - 3 between "mi", 2 for 'i'
+    assert_eq!(font.measure("mi"), (7, 8));
+    assert_eq!(font.advances("mim"), vec![5, 3, 7]);
+    assert_eq!(font.x_at_index("mim", 2), 8);
+    // '?' is printed as 'i'
+    assert_eq!(font.char_width('?'), 2);
+    assert_eq!(font.get_kerning('m', 'i'), -3);
+    assert_eq!(font.get_kerning('i', 'm'), 0);
+
+    assert!(font.load_metrics("[]").is_err());
+    assert!(font.load_metrics(r#"{ "fallback": "ab" }"#).is_err());
+    assert!(font
+        .load_metrics(r#"{ "chars": { "x": { "advance": 1 } } }"#)
+        .is_err());
+    assert!(font.load_metrics(r#"{ "size": 12 }"#).is_err());
+
+    // a mistake anywhere leaves the Font as it was
+    let version = font.version;
+    assert!(font
+        .load_metrics(r#"{ "letter_spacing": 3, "size": 1 }"#)
+        .is_err());
+    assert!(font
+        .load_metrics(r#"{ "baseline": 2, "chars": { "x": {} } }"#)
+        .is_err());
+    assert_eq!((font.get_letter_spacing(), font.get_baseline()), (1, 8));
+    assert_eq!(font.version, version);
+
+    // numbers too big for an i32 aren't cut down to fit
+    assert!(font
+        .load_metrics(r#"{ "letter_spacing": 3000000000 }"#)
+        .is_err());
+    assert!(font.load_metrics(r#"{ "baseline": -1 }"#).is_err());
+}
+
+#[test]
+fn font_layout() {
+    let mut font = test_font();
+    let layout = font.layout("mi\ni");
+    assert_eq!((layout.width, layout.height), (8, 16));
+    let dests: Vec<(i32, i32)> = layout.glyphs.iter().map(|g| (g.1.x(), g.1.y())).collect();
+    assert_eq!(dests, vec![(0, 0), (6, 0), (0, 8)]);
+    assert_eq!(layout.glyphs[1].0, shape::Rect::new(0, 0, 2, 8));
+
+    // anything that changes the layout changes the version
+    let version = font.version;
+    font.set_kerning('m', 'i', -1);
+    assert!(font.version != version);
+    assert!(test_font().version != font.version);
+}
+
+#[test]
+fn font_fallback_fonts() {
+    let mut other = test_font();
+    other.chars.clear();
+    other.chars.insert(
+        'x',
+        Glyph {
+            rect: Some(shape::Rect::new(1, 0, 3, 4)),
+            offset: (0, 0),
+            advance: 4,
+            on_baseline: false,
+        },
+    );
+    other.baseline = 4;
+    other.image.set_pixel(1, 0, 1, 2, 3, 4);
+
+    let mut font = test_font();
+    font.add_fallback_font(other);
+    assert!(font.is_printable('x'));
+    assert_eq!(font.char_width('x'), 4);
+    // copied in below the characters that were already there, and lined up on the baseline
+    let glyph = font.glyph('x').unwrap();
+    assert_eq!(glyph.rect, Some(shape::Rect::new(0, 9, 3, 4)));
+    assert_eq!(glyph.offset_from_top(font.baseline), (0, 4));
+    assert_eq!(font.image.get_pixel(0, 9), (1, 2, 3, 4));
+    // and it stays on the baseline when that moves, like the characters printed next to it
+    font.set_baseline(10);
+    assert_eq!(font.glyph_rects(&[('x', 0, 0)])[0].1.y(), 6);
+    font.set_char_metrics('x', (1, 2), 4).unwrap();
+    assert_eq!(font.glyph('x').unwrap().offset_from_top(10), (1, 2));
+
+    // accents the font doesn't have take no room, and the cursor doesn't go inside them
+    assert_eq!(font.measure("i\u{301}"), (2, 8));
+    assert_eq!(font.index_at_x("i\u{301}m", 3), 3);
+    assert!(font.glyph('\u{301}').is_none());
+}
+
+#[test]
+fn bmfont_page_grid() {
+    // four big pages make a square, not a tower
+    let grid = page_grid(&[(4096, 4096); 4]).unwrap();
+    assert_eq!((grid.width, grid.height), (8192, 8192));
+    assert_eq!(
+        grid.corners,
+        vec![(0, 0), (4096, 0), (0, 4096), (4096, 4096)]
+    );
+
+    // pages of different sizes each get a cell as big as the biggest
+    let grid = page_grid(&[(10, 20), (30, 5), (1, 1)]).unwrap();
+    assert_eq!((grid.width, grid.height), (60, 40));
+    assert_eq!(grid.corners, vec![(0, 0), (30, 0), (0, 20)]);
+
+    assert_eq!(page_grid(&[]).unwrap().corners, vec![]);
+    assert_eq!(page_grid(&[(u32::MAX, 1), (1, 1)]), None);
+}
+
+#[test]
+fn font_print_lines() {
+    let font = test_font();
+    let text = "mi\ni m\n";
+    let (placed, width, height) = font.place_lines(text);
+    assert_eq!(
+        placed,
+        vec![
+            ('m', 0, 0),
+            ('i', 6, 0),
+            ('i', 0, 8),
+            (' ', 2, 8),
+            ('m', 4, 8)
+        ]
+    );
+    // print covers the same area that measure_text reports
+    assert_eq!((width, height), (10, 24));
+    assert_eq!(font.measure(text), (width, height));
+}
+
+#[test]
+fn font_image_growth() {
+    assert_eq!(grown_size(10, 8), Some(10));
+    assert_eq!(grown_size(10, 11), Some(20));
+    assert_eq!(grown_size(0, 3), Some(4));
+    assert_eq!(grown_size(3000, 5000), None);
+    assert_eq!(grown_size(5000, 4000), Some(5000));
+
+    let mut font = test_font();
+    assert_eq!(font.allocate(3, 4), Some(shape::Rect::new(0, 9, 3, 4)));
+    assert_eq!((font.image.get_width(), font.image.get_height()), (10, 16));
+    // characters that don't fit are left out, without disturbing the rest
+    assert_eq!(font.allocate(MAX_FONT_IMAGE_SIZE + 1, 1), None);
+    assert_eq!(font.allocate(1, MAX_FONT_IMAGE_SIZE), None);
+    assert_eq!(font.allocate(2, 2), Some(shape::Rect::new(4, 9, 2, 2)));
+}
+
+/// Build the RGBA pixels of a one-row-tall image font from a string where '|' is a separator
+/// column and anything else is part of a character.
+#[cfg(test)]
+fn image_font_pixels(columns: &str) -> Vec<u8> {
+    columns
+        .chars()
+        .flat_map(|c| match c {
+            '|' => vec![255, 0, 255, 255],
+            '.' => vec![0, 0, 0, 0],
+            _ => vec![255, 255, 255, 255],
+        })
+        .collect()
+}
+
+#[test]
+fn image_font_glyphs() {
+    let pixels = image_font_pixels("|#.|##||.#");
+    let chars = parse_image_font_glyphs(10, 1, &pixels, "abc").unwrap();
+    assert_eq!(chars.len(), 3);
+    assert_eq!(chars[&'a'].rect, Some(shape::Rect::new(1, 0, 2, 1)));
+    assert_eq!(chars[&'b'].rect, Some(shape::Rect::new(4, 0, 2, 1)));
+    assert_eq!(chars[&'b'].advance, 2);
+    // the last character may run up to the edge
+    assert_eq!(chars[&'c'].rect, Some(shape::Rect::new(8, 0, 2, 1)));
+
+    // every channel of the separator color counts
+    let mut pixels = image_font_pixels("|#|#");
+    pixels[4..8].copy_from_slice(&[255, 0, 255, 0]);
+    assert_eq!(
+        parse_image_font_glyphs(4, 1, &pixels, "ab").unwrap().len(),
+        2
+    );
+}
+
+#[test]
+fn image_font_glyph_errors() {
+    let pixels = image_font_pixels("|#|#|");
+    assert!(parse_image_font_glyphs(5, 1, &pixels, "ab").is_ok());
+    // too many or too few characters in the string
+    assert!(parse_image_font_glyphs(5, 1, &pixels, "a").is_err());
+    assert!(parse_image_font_glyphs(5, 1, &pixels, "abc").is_err());
+    assert!(parse_image_font_glyphs(5, 1, &pixels, "aa").is_err());
+    // pixels that don't match the size
+    assert!(parse_image_font_glyphs(4, 1, &pixels, "ab").is_err());
+    assert!(parse_image_font_glyphs(0, 0, &[], "").is_err());
+    assert!(parse_image_font_glyphs(0x8000_0000, 2, &[], "ab").is_err());
 }